@@ -0,0 +1,130 @@
+use std::collections::BTreeSet;
+
+use bevy_ecs::{
+    change_detection::DetectChangesMut,
+    component::Component,
+    entity::Entity,
+    query::Changed,
+    system::{Commands, Query},
+    world::EntityMut,
+};
+
+use crate::Parents;
+
+/// Marks an entity whose [`Parents`] or [`Children`] edges were changed by this crate.
+///
+/// The marker is inserted the first time an edge of the entity changes, and flagged as changed
+/// on every later change. Unlike [`HierarchyEvent`]s, which are dropped after two frames, this
+/// can be observed with a `Changed<HierarchyChanged>` filter by systems that run less often.
+/// It also catches changes that removed the [`Parents`] or [`Children`] component entirely.
+///
+/// [`Children`]: crate::Children
+/// [`HierarchyEvent`]: crate::HierarchyEvent
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HierarchyChanged;
+
+/// Marks an entity some of whose descendants had their edges changed.
+///
+/// Maintained by [`propagate_descendants_changed`], which flags it as changed on every
+/// ancestor of an entity with a changed [`HierarchyChanged`].
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DescendantsChanged;
+
+/// Inserts [`HierarchyChanged`] on the entity, or flags it as changed if it is already present.
+pub(crate) fn mark_hierarchy_changed(entity: &mut EntityMut) {
+    if let Some(mut marker) = entity.get_mut::<HierarchyChanged>() {
+        marker.set_changed();
+    } else {
+        entity.insert(HierarchyChanged);
+    }
+}
+
+/// System that flags [`DescendantsChanged`] on every ancestor of an entity whose
+/// [`HierarchyChanged`] changed since the system last ran.
+///
+/// Each ancestor is flagged once, even if it is reached along several paths.
+/// Missing [`DescendantsChanged`] components are inserted through [`Commands`].
+pub fn propagate_descendants_changed(
+    mut commands: Commands,
+    changed: Query<Entity, Changed<HierarchyChanged>>,
+    parents: Query<&Parents>,
+    mut descendants_changed: Query<&mut DescendantsChanged>,
+) {
+    let mut visited = BTreeSet::new();
+    let mut nexts = changed.iter().collect::<Vec<_>>();
+    while let Some(entity) = nexts.pop() {
+        for &parent in parents.get(entity).into_iter().flatten() {
+            if visited.insert(parent) {
+                nexts.push(parent);
+            }
+        }
+    }
+
+    for ancestor in visited {
+        if let Ok(mut marker) = descendants_changed.get_mut(ancestor) {
+            marker.set_changed();
+        } else if let Some(mut entity_commands) = commands.get_entity(ancestor) {
+            entity_commands.insert(DescendantsChanged);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{entity::Entity, query::Changed, schedule::Schedule, world::World};
+
+    use super::{propagate_descendants_changed, DescendantsChanged, HierarchyChanged};
+    use crate::BuildWorldChildren;
+
+    #[test]
+    fn hierarchy_changed() {
+        let world = &mut World::new();
+        let mut changed = world.query_filtered::<Entity, Changed<HierarchyChanged>>();
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        world.entity_mut(a).push_children(&[b, c]);
+        let mut result = changed.iter(world).collect::<Vec<_>>();
+        result.sort();
+        assert_eq!(result, [a, b, c]);
+        world.clear_trackers();
+
+        // Removing the last child removes `Children`, which `Changed<Children>` can't observe.
+        world.entity_mut(a).remove_children(&[b, c]);
+        let mut result = changed.iter(world).collect::<Vec<_>>();
+        result.sort();
+        assert_eq!(result, [a, b, c]);
+        world.clear_trackers();
+
+        world.entity_mut(d).with_children(|parent| {
+            parent.spawn_empty();
+        });
+        assert_eq!(changed.iter(world).count(), 2);
+        world.clear_trackers();
+        assert_eq!(changed.iter(world).count(), 0);
+    }
+
+    #[test]
+    fn descendants_changed() {
+        let world = &mut World::new();
+        let mut schedule = Schedule::new();
+        schedule.add_systems(propagate_descendants_changed);
+        let mut changed = world.query_filtered::<Entity, Changed<DescendantsChanged>>();
+
+        let [a, b, c, d, e] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        // a -> b, c; b -> d; c -> d
+        world.entity_mut(a).push_children(&[b, c]);
+        world.entity_mut(b).push_children(&[d]);
+        world.entity_mut(c).push_children(&[d]);
+        schedule.run(world);
+        world.clear_trackers();
+
+        world.entity_mut(d).push_children(&[e]);
+        schedule.run(world);
+
+        let mut result = changed.iter(world).collect::<Vec<_>>();
+        result.sort();
+        assert_eq!(result, [a, b, c, d]);
+    }
+}
@@ -1,30 +1,52 @@
-use std::collections::BTreeSet;
+use std::{collections::BTreeSet, ops::Deref};
 
-use crate::{Children, HierarchyEvent, Parents};
+use crate::{
+    change_detection::mark_hierarchy_changed, journal::journal_despawn, Children, HierarchyChanged,
+    HierarchyError, HierarchyEvent, HierarchyJournal, MergeConflicts, MergeNodes, Parents,
+};
 use bevy_ecs::{
     bundle::Bundle,
+    component::Component,
     entity::Entity,
     prelude::Events,
     system::{Command, Commands, EntityCommands},
-    world::{EntityMut, World},
+    world::{EntityMut, EntityRef, World},
 };
+use bevy_utils::tracing::warn;
 
 // Do not use `world.send_event_batch` as it prints error message when the Events are not available in the world,
 // even though it's a valid use case to execute commands on a world without events. Loading a GLTF file for example
-fn push_events(world: &mut World, events: impl IntoIterator<Item = HierarchyEvent>) {
+pub(crate) fn push_events(world: &mut World, events: impl IntoIterator<Item = HierarchyEvent>) {
+    let tick = world.read_change_tick();
+    // Events are only buffered when there is a journal to record them.
+    match world.get_resource_mut::<HierarchyJournal>() {
+        Some(mut journal) => {
+            let events = events.into_iter().collect::<Vec<_>>();
+            journal.record(&events, tick);
+            send_events(world, events);
+        }
+        None => send_events(world, events),
+    }
+}
+
+fn send_events(world: &mut World, events: impl IntoIterator<Item = HierarchyEvent>) {
     if let Some(mut moved) = world.get_resource_mut::<Events<HierarchyEvent>>() {
         moved.extend(events);
     }
 }
 
-fn insert_children_unidirectional(world: &mut World, children: &[Entity], parent: Entity) {
+pub(crate) fn insert_children_unidirectional(
+    world: &mut World,
+    children: &[Entity],
+    parent: Entity,
+) {
     let mut entity_ext = world.entity_mut(parent);
     entity_ext.insert_children_unidirectional(children);
 }
-fn insert_parent_unidirectional(world: &mut World, child: Entity, parent: Entity) {
+pub(crate) fn insert_parent_unidirectional(world: &mut World, child: Entity, parent: Entity) {
     world.entity_mut(child).insert_parent_unidirectional(parent);
 }
-fn remove_parent_unidirectional(world: &mut World, child: Entity, parent: Entity) {
+pub(crate) fn remove_parent_unidirectional(world: &mut World, child: Entity, parent: Entity) {
     world.entity_mut(child).remove_parent_unidirectional(parent);
 }
 pub(crate) fn remove_children_unidirectional(
@@ -37,70 +59,51 @@ pub(crate) fn remove_children_unidirectional(
         .remove_children_unidirectional(children);
 }
 
-/// Update the [`Parent`] component of the `child`.
-/// Removes the `child` from the previous parent's [`Children`].
-///
-/// Does not update the new parents [`Children`] component.
-///
-/// Does nothing if `child` was already a child of `parent`.
-///
-/// Sends [`HierarchyEvent`]'s.
-// fn update_old_parent(world: &mut World, child: Entity, parent: Entity, new_parent: Entity) {
-//     let previous = update_parent_unidirectional(world, child, parent);
-//     if let Some(previous_parent) = previous {
-//         // Do nothing if the child was already parented to this entity.
-//         if previous_parent == parent {
-//             return;
-//         }
-//         remove_from_children(world, previous_parent, child);
-
-//         push_events(
-//             world,
-//             [HierarchyEvent::ChildMoved {
-//                 child,
-//                 previous_parent,
-//                 new_parent: parent,
-//             }],
-//         );
-//     } else {
-//         push_events(world, [HierarchyEvent::ChildAdded { child, parent }]);
-//     }
-// }
-
-/// Update the [`Parent`] components of the `children`.
-/// Removes the `children` from their previous parent's [`Children`].
+/// Moves `children` from `parent` to `new_parent`, updating both sides of every edge.
 ///
-/// Does not update the new parents [`Children`] component.
+/// For each child, sends a [`HierarchyEvent::ChildMoved`] if it was a child of `parent` and not
+/// yet a child of `new_parent`, a [`HierarchyEvent::ChildRemoved`] if it already was a child of
+/// `new_parent`, and a [`HierarchyEvent::ChildAdded`] if it was not a child of `parent`.
 ///
-/// Does nothing for a child if it was already a child of `parent`.
-///
-/// Sends [`HierarchyEvent`]'s.
-// fn update_old_parents(
-//     world: &mut World,
-//     children: &[Entity],
-//     old_parent: Entity,
-//     new_parent: Entity,
-// ) {
-//     let mut events = Vec::with_capacity(children.len());
-//     for &child in children {
-//         if let Some(previous) = update_parent(world, child, parent) {
-//             // Do nothing if the entity already has the correct parent.
-//             if parent == previous {
-//                 continue;
-//             }
-
-//             remove_from_children(world, previous, child);
-//             events.push(HierarchyEvent::ChildMoved {
-//                 child,
-//                 previous_parent: previous,
-//                 new_parent: parent,
-//             });
-//         } else {
-//             events.push(HierarchyEvent::ChildAdded { child, parent });
-//         }
-//     }
-//     push_events(world, events);
-// }
+/// Does nothing if `parent` and `new_parent` are the same entity.
+fn move_children(world: &mut World, children: &[Entity], parent: Entity, new_parent: Entity) {
+    if parent == new_parent {
+        return;
+    }
+    let mut events = Vec::with_capacity(children.len());
+    for &child in children {
+        let was_child = world
+            .get::<Children>(parent)
+            .is_some_and(|c| c.contains(&child));
+        let already_child = world
+            .get::<Children>(new_parent)
+            .is_some_and(|c| c.contains(&child));
+
+        match (was_child, already_child) {
+            (true, false) => events.push(HierarchyEvent::ChildMoved {
+                child,
+                previous_parent: parent,
+                new_parent,
+            }),
+            (true, true) => events.push(HierarchyEvent::ChildRemoved { child, parent }),
+            (false, false) => events.push(HierarchyEvent::ChildAdded {
+                child,
+                parent: new_parent,
+            }),
+            (false, true) => continue,
+        }
+
+        if was_child {
+            remove_children_unidirectional(world, &[child], parent);
+            remove_parent_unidirectional(world, child, parent);
+        }
+        if !already_child {
+            insert_children_unidirectional(world, &[child], new_parent);
+            insert_parent_unidirectional(world, child, new_parent);
+        }
+    }
+    push_events(world, events);
+}
 
 /// Removes entities in `children` from `parent`'s [`Children`], removing the component if it ends up empty.
 /// Also removes [`Parent`] component from `children`.
@@ -125,44 +128,305 @@ fn remove_children(parent: Entity, children: &[Entity], world: &mut World) {
     remove_children_unidirectional(world, children, parent);
 }
 
+/// Removes the children of `parent` for which `predicate` returns false.
+///
+/// Sends a [`HierarchyEvent::ChildRemoved`] for every severed edge.
+fn retain_children(
+    world: &mut World,
+    parent: Entity,
+    mut predicate: impl FnMut(EntityRef) -> bool,
+) {
+    let Some(children) = world.get::<Children>(parent) else {
+        return;
+    };
+    let removed = children
+        .iter()
+        .filter(|child| {
+            world
+                .get_entity(**child)
+                .is_some_and(|child| !predicate(child))
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    remove_children(parent, &removed, world);
+}
+
+/// Adds the children of `other` to the children of `parent`, leaving them children of `other` too.
+///
+/// `parent` itself is skipped, so it never becomes its own child. Sends a
+/// [`HierarchyEvent::ChildAdded`] for every new edge.
+fn union_children_from(world: &mut World, parent: Entity, other: Entity) {
+    let Some(children) = world.get::<Children>(other).map(|c| c.to_vec()) else {
+        return;
+    };
+    let mut events = Vec::new();
+    for child in children {
+        if child == parent
+            || world
+                .get::<Children>(parent)
+                .is_some_and(|c| c.contains(&child))
+        {
+            continue;
+        }
+        insert_children_unidirectional(world, &[child], parent);
+        insert_parent_unidirectional(world, child, parent);
+        events.push(HierarchyEvent::ChildAdded { child, parent });
+    }
+    push_events(world, events);
+}
+
+/// Removes the children of `parent` that are not in `children`. Entities of `children` that are
+/// not children of `parent` are ignored.
+///
+/// Sends a [`HierarchyEvent::ChildRemoved`] for every severed edge.
+fn intersect_children_with(world: &mut World, parent: Entity, children: &[Entity]) {
+    let keep = BTreeSet::from_iter(children.iter().copied());
+    let Some(current) = world.get::<Children>(parent) else {
+        return;
+    };
+    let removed = current.difference(&keep).copied().collect::<Vec<_>>();
+    remove_children(parent, &removed, world);
+}
+
+/// Moves all children of `from` to `to`, as [`move_children`] does.
+///
+/// If `to` is a child of `from`, it stays one rather than becoming its own child.
+fn transfer_children(world: &mut World, from: Entity, to: Entity) {
+    let Some(children) = world.get::<Children>(from) else {
+        return;
+    };
+    let children = children
+        .iter()
+        .filter(|child| **child != to)
+        .copied()
+        .collect::<Vec<_>>();
+    move_children(world, &children, from, to);
+}
+
 /// Input nodes as parents. And removes them in [Children] of nodes.
+///
+/// Sends a [`HierarchyEvent::ChildRemoved`] for every severed edge.
 fn clear_children_relation(nodes: &[Entity], world: &mut World) {
-    let children = nodes
-        .iter()
-        .filter_map(|node| world.entity_mut(*node).take::<Children>())
-        .flat_map(|children| children.0.into_iter())
-        .collect::<BTreeSet<Entity>>();
-
-    for &child in children.iter() {
-        let mut entity_ext = world.entity_mut(child);
-        if let Some(mut parents_component) = entity_ext.get_mut::<Parents>() {
-            for node in nodes {
-                parents_component.remove(node);
-            }
-            if parents_component.is_empty() {
-                entity_ext.remove::<Parents>();
-            }
+    let mut events = Vec::new();
+    for &node in nodes {
+        let mut entity_ext = world.entity_mut(node);
+        let Some(children) = entity_ext.take::<Children>() else {
+            continue;
+        };
+        mark_hierarchy_changed(&mut entity_ext);
+        for child in children.0 {
+            remove_parent_unidirectional(world, child, node);
+            events.push(HierarchyEvent::ChildRemoved {
+                child,
+                parent: node,
+            });
         }
     }
+    push_events(world, events);
 }
 /// Input nodes as children. And removes them in [Parents] of nodes.
+///
+/// Sends a [`HierarchyEvent::ChildRemoved`] for every severed edge.
 fn clear_parents_relation(nodes: &[Entity], world: &mut World) {
-    let parents = nodes
-        .iter()
-        .filter_map(|node| world.entity_mut(*node).take::<Parents>())
-        .flat_map(|node| node.0.into_iter())
-        .collect::<BTreeSet<Entity>>();
-
-    for &parent in parents.iter() {
-        let mut entity_ext = world.entity_mut(parent);
-        if let Some(mut parents_component) = entity_ext.get_mut::<Children>() {
-            for node in nodes {
-                parents_component.remove(node);
+    let mut events = Vec::new();
+    for &node in nodes {
+        let mut entity_ext = world.entity_mut(node);
+        let Some(parents) = entity_ext.take::<Parents>() else {
+            continue;
+        };
+        mark_hierarchy_changed(&mut entity_ext);
+        for parent in parents.0 {
+            remove_children_unidirectional(world, &[node], parent);
+            events.push(HierarchyEvent::ChildRemoved {
+                child: node,
+                parent,
+            });
+        }
+    }
+    push_events(world, events);
+}
+
+/// Adds `parents` to the [`Parents`] of `child`, updating both sides of every new edge.
+///
+/// Sends a [`HierarchyEvent::ChildAdded`] for every edge that did not exist yet.
+fn add_parents(world: &mut World, child: Entity, parents: &[Entity]) {
+    let mut events = Vec::new();
+    for &parent in parents {
+        if world
+            .get::<Parents>(child)
+            .is_some_and(|p| p.contains(&parent))
+        {
+            continue;
+        }
+        insert_parent_unidirectional(world, child, parent);
+        insert_children_unidirectional(world, &[child], parent);
+        events.push(HierarchyEvent::ChildAdded { child, parent });
+    }
+    push_events(world, events);
+}
+
+/// Makes `parents` the only parents of `child`, keeping the edges it already has to them.
+///
+/// Sends a [`HierarchyEvent::ChildRemoved`] for every severed edge, then a
+/// [`HierarchyEvent::ChildAdded`] for every new one.
+fn replace_parents(world: &mut World, child: Entity, parents: &[Entity]) {
+    let replace = BTreeSet::from_iter(parents.iter().copied());
+    let removed = world
+        .get::<Parents>(child)
+        .into_iter()
+        .flatten()
+        .filter(|parent| !replace.contains(parent))
+        .copied()
+        .collect::<Vec<_>>();
+    let mut events = Vec::new();
+    for parent in removed {
+        remove_parent_unidirectional(world, child, parent);
+        remove_children_unidirectional(world, &[child], parent);
+        events.push(HierarchyEvent::ChildRemoved { child, parent });
+    }
+    push_events(world, events);
+    add_parents(world, child, parents);
+}
+
+/// Makes `parents` the only parents of `child` in one operation, keeping the edges it already has
+/// to them.
+///
+/// If exactly one edge is severed and exactly one is created, sends a single
+/// [`HierarchyEvent::ChildMoved`]. Otherwise there is no unambiguous move, and a
+/// [`HierarchyEvent::ChildRemoved`] is sent per severed edge, then a [`HierarchyEvent::ChildAdded`]
+/// per new one.
+///
+/// If `check_cycles` is true and one of `parents` is `child` or one of its descendants, nothing is
+/// changed and [`HierarchyError::Cycle`] is returned.
+fn reparent(
+    world: &mut World,
+    child: Entity,
+    parents: &[Entity],
+    check_cycles: bool,
+) -> Result<(), HierarchyError> {
+    let parents = BTreeSet::from_iter(parents.iter().copied());
+    if check_cycles {
+        let descendants = reachable::<Children>(world, &[child]);
+        if let Some(&parent) = parents.iter().find(|parent| descendants.contains(parent)) {
+            return Err(HierarchyError::Cycle { parent, child });
+        }
+    }
+    let current = world
+        .get::<Parents>(child)
+        .map(|p| p.0.clone())
+        .unwrap_or_default();
+    let removed = current.difference(&parents).copied().collect::<Vec<_>>();
+    let added = parents.difference(&current).copied().collect::<Vec<_>>();
+
+    let events = match (removed.as_slice(), added.as_slice()) {
+        (&[previous_parent], &[new_parent]) => vec![HierarchyEvent::ChildMoved {
+            child,
+            previous_parent,
+            new_parent,
+        }],
+        _ => removed
+            .iter()
+            .map(|&parent| HierarchyEvent::ChildRemoved { child, parent })
+            .chain(
+                added
+                    .iter()
+                    .map(|&parent| HierarchyEvent::ChildAdded { child, parent }),
+            )
+            .collect(),
+    };
+    for &parent in &removed {
+        remove_parent_unidirectional(world, child, parent);
+        remove_children_unidirectional(world, &[child], parent);
+    }
+    for &parent in &added {
+        insert_parent_unidirectional(world, child, parent);
+        insert_children_unidirectional(world, &[child], parent);
+    }
+    push_events(world, events);
+    Ok(())
+}
+
+/// Returns `roots` and every entity reachable from them through the edges stored in `C`.
+pub(crate) fn reachable<C>(world: &World, roots: &[Entity]) -> BTreeSet<Entity>
+where
+    C: Component + Deref<Target = BTreeSet<Entity>>,
+{
+    let mut visited = BTreeSet::from_iter(roots.iter().copied());
+    let mut stack = roots.to_vec();
+    while let Some(entity) = stack.pop() {
+        for &next in world.get::<C>(entity).into_iter().flat_map(|c| c.iter()) {
+            if visited.insert(next) {
+                stack.push(next);
             }
-            if parents_component.is_empty() {
-                entity_ext.remove::<Parents>();
+        }
+    }
+    visited
+}
+
+/// Removes `node` from the hierarchy, adding each of its children to each of its parents.
+///
+/// For each child, sends a [`HierarchyEvent::ChildMoved`] from `node` to the first parent it was
+/// not a child of yet and a [`HierarchyEvent::ChildAdded`] for every other new edge, or a
+/// [`HierarchyEvent::ChildRemoved`] if it already was a child of every parent. Then sends a
+/// [`HierarchyEvent::ChildRemoved`] per parent of `node`. Children that are also parents of `node`
+/// are not made their own parent.
+///
+/// If `despawn` is true, `node` is then despawned, with a [`HierarchyEvent::SubtreeDespawned`].
+fn splice_out(world: &mut World, node: Entity, despawn: bool) {
+    let parents = world
+        .get::<Parents>(node)
+        .map(|p| p.to_vec())
+        .unwrap_or_default();
+    let children = world
+        .get::<Children>(node)
+        .map(|c| c.to_vec())
+        .unwrap_or_default();
+
+    let mut events = Vec::new();
+    for child in children {
+        remove_children_unidirectional(world, &[child], node);
+        remove_parent_unidirectional(world, child, node);
+        let mut moved = false;
+        for &parent in &parents {
+            let already_child = world
+                .get::<Children>(parent)
+                .is_some_and(|c| c.contains(&child));
+            if parent == child || already_child {
+                continue;
             }
+            insert_children_unidirectional(world, &[child], parent);
+            insert_parent_unidirectional(world, child, parent);
+            events.push(if moved {
+                HierarchyEvent::ChildAdded { child, parent }
+            } else {
+                HierarchyEvent::ChildMoved {
+                    child,
+                    previous_parent: node,
+                    new_parent: parent,
+                }
+            });
+            moved = true;
         }
+        if !moved {
+            events.push(HierarchyEvent::ChildRemoved {
+                child,
+                parent: node,
+            });
+        }
+    }
+    push_events(world, events);
+    clear_parents_relation(&[node], world);
+
+    if despawn {
+        journal_despawn(world, &[node]);
+        world.despawn(node);
+        push_events(
+            world,
+            [HierarchyEvent::SubtreeDespawned {
+                root: node,
+                entities: vec![node],
+            }],
+        );
     }
 }
 
@@ -202,8 +466,8 @@ impl Command for MoveChild {
 /// Command that pushes children to the end of the entity's [`Children`].
 #[derive(Debug)]
 pub struct PushChildren {
-    parent: Entity,
-    children: Vec<Entity>,
+    pub(crate) parent: Entity,
+    pub(crate) children: Vec<Entity>,
 }
 
 impl Command for PushChildren {
@@ -214,9 +478,9 @@ impl Command for PushChildren {
 /// Command that moves children to the end of the entity's [`Children`].
 #[derive(Debug)]
 pub struct MoveChildren {
-    parent: Entity,
-    children: Vec<Entity>,
-    new_parent: Entity,
+    pub(crate) parent: Entity,
+    pub(crate) children: Vec<Entity>,
+    pub(crate) new_parent: Entity,
 }
 
 impl Command for MoveChildren {
@@ -229,8 +493,8 @@ impl Command for MoveChildren {
 
 /// Command that removes children from an entity, and removes these children's parent.
 pub struct RemoveChildren {
-    parent: Entity,
-    children: Vec<Entity>,
+    pub(crate) parent: Entity,
+    pub(crate) children: Vec<Entity>,
 }
 
 impl Command for RemoveChildren {
@@ -242,56 +506,195 @@ impl Command for RemoveChildren {
 /// Command that clears all children from an entity and removes [`Parent`] component from those
 /// children.
 pub struct ClearChildren {
-    parent: Entity,
+    pub(crate) parent: Entity,
 }
 
 impl Command for ClearChildren {
     fn apply(self, world: &mut World) {
-        let entity_ext = world.entity_mut(self.parent);
-        let children = entity_ext
-            .get::<Children>()
-            .map(|children| children.to_vec())
-            .unwrap_or_default();
-        remove_children(self.parent, children.as_slice(), world);
+        world.entity_mut(self.parent).clear_children();
     }
 }
 
 /// Command that clear all children from an entity, replacing them with the given children.
 pub struct ReplaceChildren {
-    parent: Entity,
-    children: Vec<Entity>,
+    pub(crate) parent: Entity,
+    pub(crate) children: Vec<Entity>,
 }
 
 impl Command for ReplaceChildren {
     fn apply(self, world: &mut World) {
-        let mut entity_ext = world.entity_mut(self.parent);
-        let replace = BTreeSet::from_iter(self.children);
-        let parents = entity_ext.get::<Children>().unwrap();
-        let remove_different = parents
-            .difference(&replace)
-            .copied()
-            .collect::<Vec<Entity>>();
-        let insert_different = replace
-            .difference(parents)
-            .copied()
-            .collect::<Vec<Entity>>();
+        world
+            .entity_mut(self.parent)
+            .replace_children(&self.children);
+    }
+}
+
+/// Command that removes all parents of an entity, and removes that entity from their [`Children`].
+#[derive(Debug)]
+pub struct RemoveParents {
+    /// `Entity` whose parents must be removed.
+    pub child: Entity,
+}
+
+impl Command for RemoveParents {
+    fn apply(self, world: &mut World) {
+        clear_parents_relation(&[self.child], world);
+    }
+}
+
+/// Command that adds parents to an entity.
+#[derive(Debug)]
+pub struct AddParents {
+    /// `Entity` to add the parents to.
+    pub child: Entity,
+    /// Parents to add.
+    pub parents: Vec<Entity>,
+}
+
+impl Command for AddParents {
+    fn apply(self, world: &mut World) {
+        add_parents(world, self.child, &self.parents);
+    }
+}
+
+/// Command that replaces all parents of an entity with the given parents.
+#[derive(Debug)]
+pub struct ReplaceParents {
+    /// `Entity` whose parents must be replaced.
+    pub child: Entity,
+    /// The new parents.
+    pub parents: Vec<Entity>,
+}
+
+impl Command for ReplaceParents {
+    fn apply(self, world: &mut World) {
+        replace_parents(world, self.child, &self.parents);
+    }
+}
+
+/// Command that removes the children of an entity for which a predicate returns false.
+pub struct RetainChildren<F> {
+    /// Parent entity whose children are filtered.
+    pub parent: Entity,
+    /// Called with every child, which is kept if it returns true.
+    pub predicate: F,
+}
+
+impl<F: FnMut(EntityRef) -> bool + Send + 'static> Command for RetainChildren<F> {
+    fn apply(self, world: &mut World) {
+        retain_children(world, self.parent, self.predicate);
+    }
+}
+
+/// Command that adds the children of another entity to an entity's children.
+#[derive(Debug)]
+pub struct UnionChildren {
+    /// Parent entity receiving the children.
+    pub parent: Entity,
+    /// Entity whose children are added, and which keeps them.
+    pub other: Entity,
+}
+
+impl Command for UnionChildren {
+    fn apply(self, world: &mut World) {
+        union_children_from(world, self.parent, self.other);
+    }
+}
+
+/// Command that removes the children of an entity that are not in the given list.
+#[derive(Debug)]
+pub struct IntersectChildren {
+    /// Parent entity whose children are filtered.
+    pub parent: Entity,
+    /// Children to keep.
+    pub children: Vec<Entity>,
+}
+
+impl Command for IntersectChildren {
+    fn apply(self, world: &mut World) {
+        intersect_children_with(world, self.parent, &self.children);
+    }
+}
+
+/// Command that moves all children of an entity to another entity.
+#[derive(Debug)]
+pub struct TransferChildren {
+    /// Entity losing its children.
+    pub from: Entity,
+    /// Entity receiving the children.
+    pub to: Entity,
+}
+
+impl Command for TransferChildren {
+    fn apply(self, world: &mut World) {
+        transfer_children(world, self.from, self.to);
+    }
+}
+
+/// Command that replaces all parents of an entity in one operation.
+///
+/// Unlike [`ReplaceParents`], replacing exactly one parent with another is reported as a single
+/// move. If `check_cycles` is true and the new parents would create a cycle, a warning is
+/// logged and nothing is changed.
+#[derive(Debug)]
+pub struct Reparent {
+    /// `Entity` whose parents must be replaced.
+    pub child: Entity,
+    /// The new parents.
+    pub parents: Vec<Entity>,
+    /// Whether to reject new parents that are descendants of `child`.
+    pub check_cycles: bool,
+}
+
+impl Command for Reparent {
+    fn apply(self, world: &mut World) {
+        if let Err(error) = reparent(world, self.child, &self.parents, self.check_cycles) {
+            warn!("Failed to reparent {:?}: {error}", self.child);
+        }
+    }
+}
 
-        entity_ext.remove_children(remove_different.as_slice());
-        entity_ext.push_children(insert_different.as_slice());
+/// Command that removes every edge to an entity's parents and children, keeping it alive.
+#[derive(Debug)]
+pub struct Isolate {
+    /// `Entity` to isolate.
+    pub entity: Entity,
+}
+
+impl Command for Isolate {
+    fn apply(self, world: &mut World) {
+        world.entity_mut(self.entity).isolate();
+    }
+}
+
+/// Command that removes every edge to an entity's parents and children, then despawns it.
+#[derive(Debug)]
+pub struct Clear {
+    /// `Entity` to clear.
+    pub entity: Entity,
+}
+
+impl Command for Clear {
+    fn apply(self, world: &mut World) {
+        world.entity_mut(self.entity).clear();
     }
 }
 
-// /// Command that removes the parent of an entity, and removes that entity from the parent's [`Children`].
-// pub struct RemoveParents {
-//     /// `Entity` whose parent must be removed.
-//     pub child: Entity,
-// }
+/// Command that removes an entity from the hierarchy, connecting its parents to its children.
+#[derive(Debug)]
+pub struct SpliceOut {
+    /// `Entity` to splice out.
+    pub entity: Entity,
+    /// Whether to despawn `entity` afterwards.
+    pub despawn: bool,
+}
+
+impl Command for SpliceOut {
+    fn apply(self, world: &mut World) {
+        splice_out(world, self.entity, self.despawn);
+    }
+}
 
-// impl Command for RemoveParents {
-//     fn apply(self, world: &mut World) {
-//         world.entity_mut(self.child).remove_parents();
-//     }
-// }
 /// Command that removes the parent of an entity, and removes that entity from the parent's [`Children`].
 pub struct RemoveParent {
     /// `Entity` whose parent must be removed.
@@ -377,6 +780,20 @@ pub trait BuildChildren {
     ///
     /// The removed children will have their [`Parent`] component removed.
     fn replace_children(&mut self, children: &[Entity]) -> &mut Self;
+    /// Removes the children for which `predicate` returns false.
+    ///
+    /// The removed children have this entity removed from their [`Parents`].
+    fn retain_children<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: FnMut(EntityRef) -> bool + Send + 'static;
+    /// Adds the children of `other` to this entity, leaving them children of `other` too.
+    fn union_children_from(&mut self, other: Entity) -> &mut Self;
+    /// Removes the children that are not in `children`.
+    fn intersect_children_with(&mut self, children: &[Entity]) -> &mut Self;
+    /// Moves all children of this entity to `to`.
+    ///
+    /// If `to` is itself a child of this entity, it stays one.
+    fn transfer_children(&mut self, to: Entity) -> &mut Self;
     /// Sets the parent of this entity.
     ///
     /// If this entity already had a parent, the parent's [`Children`] component will have this
@@ -388,6 +805,45 @@ pub trait BuildChildren {
     /// Also removes this entity from its parent's [`Children`] component. Removing all children from a parent causes
     /// its [`Children`] component to be removed from the entity.
     fn remove_parent(&mut self, parent: Entity) -> &mut Self;
+    /// Adds the given parents to this entity, keeping its current parents.
+    fn add_parents(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Removes all current parents from this entity, replacing them with the given parents.
+    ///
+    /// Edges to parents that are in both sets are kept.
+    fn replace_parents(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Replaces all parents of this entity in one operation.
+    ///
+    /// If exactly one parent is replaced by another, this is reported as a
+    /// [`HierarchyEvent::ChildMoved`]. Otherwise every severed edge is reported as a
+    /// [`HierarchyEvent::ChildRemoved`] and every new one as a [`HierarchyEvent::ChildAdded`].
+    fn reparent(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Same as `reparent`, but leaves the parents unchanged and logs a warning if one of `parents`
+    /// is this entity or one of its descendants.
+    fn reparent_checked(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Removes all parents from this entity, and removes it from their [`Children`].
+    fn clear_parents(&mut self) -> &mut Self;
+    /// Removes every edge to this entity's parents and children, keeping it alive.
+    ///
+    /// The entity can be inserted back into the hierarchy later.
+    fn isolate(&mut self) -> &mut Self;
+    /// Same as `isolate`.
+    fn detach_all(&mut self) -> &mut Self;
+    /// Removes every edge to this entity's parents and children, then despawns it.
+    ///
+    /// Unlike [`DespawnRecursiveExt::despawn_recursive`], the children are kept alive.
+    ///
+    /// [`DespawnRecursiveExt::despawn_recursive`]: crate::DespawnRecursiveExt::despawn_recursive
+    fn clear(self);
+    /// Removes this entity from the hierarchy, adding each of its children to each of its parents.
+    ///
+    /// Edges that already exist are kept as they are. If `despawn` is true, this entity is then
+    /// despawned, otherwise it is left without parents and children.
+    fn splice_out(self, despawn: bool);
+    /// Merges `source` into this entity, taking over its parents and children, then despawns
+    /// `source`.
+    ///
+    /// See [`merge_nodes`](crate::merge_nodes) for how conflicting edges are handled.
+    fn merge_from(&mut self, source: Entity, conflicts: MergeConflicts) -> &mut Self;
 }
 
 impl<'w, 's, 'a> BuildChildren for EntityCommands<'w, 's, 'a> {
@@ -464,6 +920,36 @@ impl<'w, 's, 'a> BuildChildren for EntityCommands<'w, 's, 'a> {
         self
     }
 
+    fn retain_children<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: FnMut(EntityRef) -> bool + Send + 'static,
+    {
+        let parent = self.id();
+        self.commands().add(RetainChildren { parent, predicate });
+        self
+    }
+
+    fn union_children_from(&mut self, other: Entity) -> &mut Self {
+        let parent = self.id();
+        self.commands().add(UnionChildren { parent, other });
+        self
+    }
+
+    fn intersect_children_with(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        self.commands().add(IntersectChildren {
+            parent,
+            children: Vec::from(children),
+        });
+        self
+    }
+
+    fn transfer_children(&mut self, to: Entity) -> &mut Self {
+        let from = self.id();
+        self.commands().add(TransferChildren { from, to });
+        self
+    }
+
     fn set_parent(&mut self, parent: Entity) -> &mut Self {
         let child = self.id();
         self.commands().add(AddChild { child, parent });
@@ -475,10 +961,84 @@ impl<'w, 's, 'a> BuildChildren for EntityCommands<'w, 's, 'a> {
         self.commands().add(RemoveParent { child, parent });
         self
     }
-}
 
-/// Struct for adding children to an entity directly through the [`World`] for use in exclusive systems.
-#[derive(Debug)]
+    fn add_parents(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.commands().add(AddParents {
+            child,
+            parents: Vec::from(parents),
+        });
+        self
+    }
+
+    fn replace_parents(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.commands().add(ReplaceParents {
+            child,
+            parents: Vec::from(parents),
+        });
+        self
+    }
+
+    fn reparent(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.commands().add(Reparent {
+            child,
+            parents: parents.to_vec(),
+            check_cycles: false,
+        });
+        self
+    }
+
+    fn reparent_checked(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.commands().add(Reparent {
+            child,
+            parents: parents.to_vec(),
+            check_cycles: true,
+        });
+        self
+    }
+
+    fn clear_parents(&mut self) -> &mut Self {
+        let child = self.id();
+        self.commands().add(RemoveParents { child });
+        self
+    }
+
+    fn isolate(&mut self) -> &mut Self {
+        let entity = self.id();
+        self.commands().add(Isolate { entity });
+        self
+    }
+
+    fn detach_all(&mut self) -> &mut Self {
+        self.isolate()
+    }
+
+    fn clear(mut self) {
+        let entity = self.id();
+        self.commands().add(Clear { entity });
+    }
+
+    fn splice_out(mut self, despawn: bool) {
+        let entity = self.id();
+        self.commands().add(SpliceOut { entity, despawn });
+    }
+
+    fn merge_from(&mut self, source: Entity, conflicts: MergeConflicts) -> &mut Self {
+        let target = self.id();
+        self.commands().add(MergeNodes {
+            target,
+            source,
+            conflicts,
+        });
+        self
+    }
+}
+
+/// Struct for adding children to an entity directly through the [`World`] for use in exclusive systems.
+#[derive(Debug)]
 pub struct WorldChildBuilder<'w> {
     world: &'w mut World,
     parent: Entity,
@@ -487,11 +1047,16 @@ pub struct WorldChildBuilder<'w> {
 impl<'w> WorldChildBuilder<'w> {
     /// Spawns an entity with the given bundle and inserts it into the parent entity's [`Children`].
     /// Also adds [`Parents`] component to the created entity.
+    #[allow(clippy::implied_bounds_in_impls)]
     pub fn spawn(&mut self, bundle: impl Bundle + Send + Sync + 'static) -> EntityMut<'_> {
         // insert_parent_unidirectional(self.world, entity, self.parent);
         let entity = self
             .world
-            .spawn((bundle, Parents(BTreeSet::from([self.parent]))))
+            .spawn((
+                bundle,
+                Parents(BTreeSet::from([self.parent])),
+                HierarchyChanged,
+            ))
             .id();
         insert_children_unidirectional(self.world, &[entity], self.parent);
 
@@ -510,7 +1075,7 @@ impl<'w> WorldChildBuilder<'w> {
     pub fn spawn_empty(&mut self) -> EntityMut<'_> {
         let entity = self
             .world
-            .spawn(Parents(BTreeSet::from([self.parent])))
+            .spawn((Parents(BTreeSet::from([self.parent])), HierarchyChanged))
             .id();
         insert_children_unidirectional(self.world, &[entity], self.parent);
         push_events(
@@ -565,6 +1130,29 @@ pub trait BuildWorldChildren {
     /// Removing all children from a parent causes its [`Children`] component to be removed from the entity.
     fn remove_children(&mut self, children: &[Entity]) -> &mut Self;
 
+    /// Removes all children from this entity. The [`Children`] component will be removed if it exists, otherwise this does nothing.
+    fn clear_children(&mut self) -> &mut Self;
+
+    /// Removes all current children from this entity, replacing them with the specified list of entities.
+    ///
+    /// Edges to children that are in both lists are kept.
+    fn replace_children(&mut self, children: &[Entity]) -> &mut Self;
+
+    /// Removes the children for which `predicate` returns false.
+    ///
+    /// The removed children have this entity removed from their [`Parents`].
+    fn retain_children<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: FnMut(EntityRef) -> bool + Send + 'static;
+    /// Adds the children of `other` to this entity, leaving them children of `other` too.
+    fn union_children_from(&mut self, other: Entity) -> &mut Self;
+    /// Removes the children that are not in `children`.
+    fn intersect_children_with(&mut self, children: &[Entity]) -> &mut Self;
+    /// Moves all children of this entity to `to`.
+    ///
+    /// If `to` is itself a child of this entity, it stays one.
+    fn transfer_children(&mut self, to: Entity) -> &mut Self;
+
     /// Sets the parent of this entity.
     ///
     /// If this entity already had a parent, the parent's [`Children`] component will have this
@@ -577,8 +1165,47 @@ pub trait BuildWorldChildren {
     /// Also removes this entity from its parent's [`Children`] component. Removing all children from a parent causes
     /// its [`Children`] component to be removed from the entity.
     fn remove_parent(&mut self, parent: Entity) -> &mut Self;
-    /// despawn a node.
+
+    /// Adds the given parents to this entity, keeping its current parents.
+    fn add_parents(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Removes all current parents from this entity, replacing them with the given parents.
+    ///
+    /// Edges to parents that are in both sets are kept.
+    fn replace_parents(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Replaces all parents of this entity in one operation.
+    ///
+    /// If exactly one parent is replaced by another, this is reported as a
+    /// [`HierarchyEvent::ChildMoved`]. Otherwise every severed edge is reported as a
+    /// [`HierarchyEvent::ChildRemoved`] and every new one as a [`HierarchyEvent::ChildAdded`].
+    fn reparent(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Same as `reparent`, but leaves the parents unchanged and returns
+    /// [`HierarchyError::Cycle`] if one of `parents` is this entity or one of its descendants.
+    fn reparent_checked(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Removes all parents from this entity, and removes it from their [`Children`].
+    fn clear_parents(&mut self) -> &mut Self;
+
+    /// Removes every edge to this entity's parents and children, keeping it alive.
+    ///
+    /// The entity can be inserted back into the hierarchy later.
+    fn isolate(&mut self) -> &mut Self;
+    /// Same as `isolate`.
+    fn detach_all(&mut self) -> &mut Self;
+    /// Removes every edge to this entity's parents and children, then despawns it.
+    ///
+    /// Unlike [`DespawnRecursiveExt::despawn_recursive`], the children are kept alive.
+    ///
+    /// [`DespawnRecursiveExt::despawn_recursive`]: crate::DespawnRecursiveExt::despawn_recursive
     fn clear(self);
+    /// Removes this entity from the hierarchy, adding each of its children to each of its parents.
+    ///
+    /// Edges that already exist are kept as they are. If `despawn` is true, this entity is then
+    /// despawned, otherwise it is left without parents and children.
+    fn splice_out(self, despawn: bool);
+    /// Merges `source` into this entity, taking over its parents and children, then despawns
+    /// `source`.
+    ///
+    /// See [`merge_nodes`](crate::merge_nodes) for how conflicting edges are handled.
+    fn merge_from(&mut self, source: Entity, conflicts: MergeConflicts) -> &mut Self;
 }
 
 impl<'w> BuildWorldChildren for EntityMut<'w> {
@@ -592,33 +1219,17 @@ impl<'w> BuildWorldChildren for EntityMut<'w> {
 
     fn move_child(&mut self, new_parent: Entity, child: Entity) -> &mut Self {
         let parent = self.id();
-
-        self.remove_children_unidirectional(&[child]);
-
         self.world_scope(|world| {
-            // let mut entity_ext = world.entity_mut(new_parent);
-            // if let Some(mut children_component) = entity_ext.get_mut::<Children>() {
-            //     children_component.insert(child);
-            // } else {
-            //     entity_ext.insert(Children::new(BTreeSet::from([child])));
-            // }
-            insert_children_unidirectional(world, &[child], new_parent);
-
-            // handle Parent
-            let mut entity_ext = world.entity_mut(child);
-            if let Some(mut parents_component) = entity_ext.get_mut::<Parents>() {
-                parents_component.remove(&parent);
-                parents_component.insert(new_parent);
-            } else {
-                entity_ext.insert(Parents::new(BTreeSet::from([new_parent])));
-            }
+            move_children(world, &[child], parent, new_parent);
         });
-
         self
     }
 
     fn add_child(&mut self, child: Entity) -> &mut Self {
         let parent = self.id();
+        if self.get::<Children>().is_some_and(|c| c.contains(&child)) {
+            return self;
+        }
 
         self.insert_children_unidirectional(&[child]);
         self.world_scope(|world| {
@@ -631,21 +1242,8 @@ impl<'w> BuildWorldChildren for EntityMut<'w> {
 
     fn move_children(&mut self, new_parent: Entity, children: &[Entity]) -> &mut Self {
         let parent = self.id();
-
-        self.remove_children_unidirectional(children);
         self.world_scope(|world| {
-            insert_children_unidirectional(world, children, new_parent);
-
-            // handle Parent
-            for &child in children {
-                let mut entity_ext = world.entity_mut(child);
-                if let Some(mut parents_component) = entity_ext.get_mut::<Parents>() {
-                    parents_component.remove(&parent);
-                    parents_component.insert(new_parent);
-                } else {
-                    entity_ext.insert(Parents::new(BTreeSet::from([new_parent])));
-                }
-            }
+            move_children(world, children, parent, new_parent);
         });
         self
     }
@@ -694,8 +1292,65 @@ impl<'w> BuildWorldChildren for EntityMut<'w> {
         self
     }
 
+    fn clear_children(&mut self) -> &mut Self {
+        let children = self
+            .get::<Children>()
+            .map(|children| children.to_vec())
+            .unwrap_or_default();
+        self.remove_children(&children)
+    }
+
+    fn replace_children(&mut self, children: &[Entity]) -> &mut Self {
+        let replace = BTreeSet::from_iter(children.iter().copied());
+        let current = self
+            .get::<Children>()
+            .map(|children| children.0.clone())
+            .unwrap_or_default();
+        let remove_different = current
+            .difference(&replace)
+            .copied()
+            .collect::<Vec<Entity>>();
+        let insert_different = replace
+            .difference(&current)
+            .copied()
+            .collect::<Vec<Entity>>();
+
+        self.remove_children(&remove_different);
+        self.push_children(&insert_different)
+    }
+
+    fn retain_children<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: FnMut(EntityRef) -> bool + Send + 'static,
+    {
+        let parent = self.id();
+        self.world_scope(|world| retain_children(world, parent, predicate));
+        self
+    }
+
+    fn union_children_from(&mut self, other: Entity) -> &mut Self {
+        let parent = self.id();
+        self.world_scope(|world| union_children_from(world, parent, other));
+        self
+    }
+
+    fn intersect_children_with(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        self.world_scope(|world| intersect_children_with(world, parent, children));
+        self
+    }
+
+    fn transfer_children(&mut self, to: Entity) -> &mut Self {
+        let from = self.id();
+        self.world_scope(|world| transfer_children(world, from, to));
+        self
+    }
+
     fn set_parent(&mut self, parent: Entity) -> &mut Self {
         let child = self.id();
+        if self.get::<Parents>().is_some_and(|p| p.contains(&parent)) {
+            return self;
+        }
         self.insert_parent_unidirectional(parent);
 
         self.world_scope(|world| {
@@ -707,6 +1362,9 @@ impl<'w> BuildWorldChildren for EntityMut<'w> {
 
     fn remove_parent(&mut self, parent: Entity) -> &mut Self {
         let child = self.id();
+        if !self.get::<Parents>().is_some_and(|p| p.contains(&parent)) {
+            return self;
+        }
 
         self.remove_parent_unidirectional(parent);
         self.world_scope(|world| {
@@ -717,13 +1375,83 @@ impl<'w> BuildWorldChildren for EntityMut<'w> {
         self
     }
 
-    fn clear(mut self) {
+    fn add_parents(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.world_scope(|world| add_parents(world, child, parents));
+        self
+    }
+
+    fn replace_parents(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.world_scope(|world| replace_parents(world, child, parents));
+        self
+    }
+
+    fn reparent(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        self.world_scope(|world| {
+            // Without the cycle check this cannot fail.
+            let _ = reparent(world, child, parents, false);
+        });
+        self
+    }
+
+    fn reparent_checked(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        let child = self.id();
+        self.world_scope(|world| reparent(world, child, parents, true))?;
+        Ok(self)
+    }
+
+    fn clear_parents(&mut self) -> &mut Self {
+        let child = self.id();
+        self.world_scope(|world| clear_parents_relation(&[child], world));
+        self
+    }
+
+    fn isolate(&mut self) -> &mut Self {
         let node = self.id();
         self.world_scope(|world| {
             clear_children_relation(&[node], world);
             clear_parents_relation(&[node], world);
         });
-        self.despawn();
+        self
+    }
+
+    fn detach_all(&mut self) -> &mut Self {
+        self.isolate()
+    }
+
+    fn clear(mut self) {
+        let node = self.id();
+        self.isolate();
+        let world = self.into_world_mut();
+        journal_despawn(world, &[node]);
+        world.despawn(node);
+        push_events(
+            world,
+            [HierarchyEvent::SubtreeDespawned {
+                root: node,
+                entities: vec![node],
+            }],
+        );
+    }
+
+    fn splice_out(self, despawn: bool) {
+        let node = self.id();
+        splice_out(self.into_world_mut(), node, despawn);
+    }
+
+    fn merge_from(&mut self, source: Entity, conflicts: MergeConflicts) -> &mut Self {
+        let target = self.id();
+        self.world_scope(|world| {
+            MergeNodes {
+                target,
+                source,
+                conflicts,
+            }
+            .apply(world);
+        });
+        self
     }
 }
 
@@ -744,6 +1472,7 @@ impl UnidirectionalExt for EntityMut<'_> {
         } else {
             self.insert(Children::new(BTreeSet::from_iter(children.iter().copied())));
         }
+        mark_hierarchy_changed(self);
     }
 
     fn remove_children_unidirectional(&mut self, children: &[Entity]) {
@@ -754,6 +1483,7 @@ impl UnidirectionalExt for EntityMut<'_> {
             if children_component.is_empty() {
                 self.remove::<Children>();
             }
+            mark_hierarchy_changed(self);
         }
     }
 
@@ -763,6 +1493,7 @@ impl UnidirectionalExt for EntityMut<'_> {
         } else {
             self.insert(Parents::new(BTreeSet::from([parent])));
         }
+        mark_hierarchy_changed(self);
     }
 
     fn remove_parent_unidirectional(&mut self, parent: Entity) {
@@ -771,6 +1502,7 @@ impl UnidirectionalExt for EntityMut<'_> {
             if parents_component.is_empty() {
                 self.remove::<Parents>();
             }
+            mark_hierarchy_changed(self);
         }
     }
 }
@@ -781,7 +1513,8 @@ mod tests {
     use super::{BuildChildren, BuildWorldChildren};
     use crate::{
         components::{Children, Parents},
-        HierarchyEvent::{self, ChildAdded, ChildRemoved},
+        HierarchyError,
+        HierarchyEvent::{self, ChildAdded, ChildMoved, ChildRemoved, SubtreeDespawned},
     };
 
     use bevy_ecs::{
@@ -863,6 +1596,10 @@ mod tests {
                 parent: a,
             }],
         );
+        // Adding an existing child again sends no event.
+        world.entity_mut(a).add_child(c);
+        world.entity_mut(c).set_parent(a);
+        assert_events(world, &[]);
         // Children component should be removed when it's empty.
         world.entity_mut(a).remove_children(&[b, c]);
         assert!(world.get::<Children>(a).is_none());
@@ -953,6 +1690,502 @@ mod tests {
                 parent: a,
             }],
         );
+
+        // Removing a parent the entity doesn't have sends no event.
+        world.entity_mut(c).remove_parent(a);
+        assert_events(world, &[]);
+    }
+
+    #[test]
+    fn edit_parents_world() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        world.entity_mut(d).add_parents(&[a, b]);
+        assert_parents(world, d, &[a, b]);
+        assert_children(world, a, &[d]);
+        omit_events(world, 2);
+
+        world.entity_mut(d).replace_parents(&[b, c]);
+        assert_parents(world, d, &[b, c]);
+        assert!(world.get::<Children>(a).is_none());
+        assert_children(world, c, &[d]);
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: d,
+                    parent: a,
+                },
+                ChildAdded {
+                    child: d,
+                    parent: c,
+                },
+            ],
+        );
+
+        world.entity_mut(d).clear_parents();
+        assert!(world.get::<Parents>(d).is_none());
+        assert!(world.get::<Children>(b).is_none());
+        assert!(world.get::<Children>(c).is_none());
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: d,
+                    parent: b,
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: c,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn edit_parents_commands() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        commands
+            .entity(d)
+            .add_parents(&[a, b])
+            .replace_parents(&[c]);
+        queue.apply(world);
+        assert_parents(world, d, &[c]);
+        assert_children(world, c, &[d]);
+        assert!(world.get::<Children>(a).is_none());
+        omit_events(world, 4);
+        assert_events(
+            world,
+            &[ChildAdded {
+                child: d,
+                parent: c,
+            }],
+        );
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, world).entity(d).clear_parents();
+        queue.apply(world);
+        assert!(world.get::<Parents>(d).is_none());
+        assert!(world.get::<Children>(c).is_none());
+    }
+
+    #[test]
+    fn reparent() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d, e, f] = std::array::from_fn(|_| world.spawn_empty().id());
+        world.entity_mut(d).add_parents(&[a, b]).push_children(&[f]);
+        omit_events(world, 3);
+
+        world.entity_mut(d).reparent(&[b, c]);
+        assert_parents(world, d, &[b, c]);
+        assert!(world.get::<Children>(a).is_none());
+        assert_children(world, c, &[d]);
+        assert_events(
+            world,
+            &[ChildMoved {
+                child: d,
+                previous_parent: a,
+                new_parent: c,
+            }],
+        );
+
+        world.entity_mut(d).reparent(&[b, e, a]);
+        assert_parents(world, d, &[a, b, e]);
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: d,
+                    parent: c,
+                },
+                ChildAdded {
+                    child: d,
+                    parent: a,
+                },
+                ChildAdded {
+                    child: d,
+                    parent: e,
+                },
+            ],
+        );
+
+        assert!(matches!(
+            world.entity_mut(d).reparent_checked(&[a, f]),
+            Err(HierarchyError::Cycle { parent, child }) if parent == f && child == d
+        ));
+        assert_parents(world, d, &[a, b, e]);
+        assert_events(world, &[]);
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, world)
+            .entity(d)
+            .reparent_checked(&[c]);
+        queue.apply(world);
+        assert_parents(world, d, &[c]);
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: d,
+                    parent: a,
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: b,
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: e,
+                },
+                ChildAdded {
+                    child: d,
+                    parent: c,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn children_set_operations() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d, e, f] = std::array::from_fn(|_| world.spawn_empty().id());
+        world.entity_mut(a).push_children(&[b, c, d]);
+        world.entity_mut(e).push_children(&[d, f]);
+        omit_events(world, 5);
+
+        world
+            .entity_mut(a)
+            .retain_children(move |child| child.id() != c);
+        assert_children(world, a, &[b, d]);
+        assert!(world.get::<Parents>(c).is_none());
+        assert_events(
+            world,
+            &[ChildRemoved {
+                child: c,
+                parent: a,
+            }],
+        );
+
+        world.entity_mut(a).union_children_from(e);
+        assert_children(world, a, &[b, d, f]);
+        assert_children(world, e, &[d, f]);
+        assert_parents(world, f, &[a, e]);
+        assert_events(
+            world,
+            &[ChildAdded {
+                child: f,
+                parent: a,
+            }],
+        );
+
+        world.entity_mut(a).intersect_children_with(&[c, d, f]);
+        assert_children(world, a, &[d, f]);
+        assert!(world.get::<Parents>(b).is_none());
+        assert_events(
+            world,
+            &[ChildRemoved {
+                child: b,
+                parent: a,
+            }],
+        );
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, world)
+            .entity(a)
+            .transfer_children(c);
+        queue.apply(world);
+        assert!(world.get::<Children>(a).is_none());
+        assert_children(world, c, &[d, f]);
+        assert_parents(world, d, &[c, e]);
+        assert_events(
+            world,
+            &[
+                ChildMoved {
+                    child: d,
+                    previous_parent: a,
+                    new_parent: c,
+                },
+                ChildMoved {
+                    child: f,
+                    previous_parent: a,
+                    new_parent: c,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn isolate() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+        world.entity_mut(a).push_children(&[b]);
+        world.entity_mut(b).push_children(&[c, d]);
+        omit_events(world, 3);
+
+        world.entity_mut(b).isolate();
+        assert!(world.get_entity(b).is_some());
+        assert!(world.get::<Parents>(b).is_none());
+        assert!(world.get::<Children>(b).is_none());
+        assert!(world.get::<Children>(a).is_none());
+        assert!(world.get::<Parents>(c).is_none());
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: c,
+                    parent: b,
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: b,
+                },
+                ChildRemoved {
+                    child: b,
+                    parent: a,
+                },
+            ],
+        );
+
+        world.entity_mut(a).push_children(&[b, c]);
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        commands.entity(c).detach_all();
+        commands.entity(a).clear();
+        queue.apply(world);
+        assert!(world.get_entity(a).is_none());
+        assert!(world.get::<Parents>(b).is_none());
+        assert!(world.get::<Parents>(c).is_none());
+    }
+
+    #[test]
+    fn splice_out() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, n, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+        world.entity_mut(n).add_parents(&[a, b]);
+        world.entity_mut(n).push_children(&[c, d]);
+        world.entity_mut(b).push_children(&[d]);
+        omit_events(world, 5);
+
+        world.entity_mut(n).splice_out(false);
+        assert!(world.get_entity(n).is_some());
+        assert!(world.get::<Parents>(n).is_none());
+        assert!(world.get::<Children>(n).is_none());
+        assert_children(world, a, &[c, d]);
+        assert_children(world, b, &[c, d]);
+        assert_parents(world, c, &[a, b]);
+        assert_parents(world, d, &[a, b]);
+        assert_events(
+            world,
+            &[
+                ChildMoved {
+                    child: c,
+                    previous_parent: n,
+                    new_parent: a,
+                },
+                ChildAdded {
+                    child: c,
+                    parent: b,
+                },
+                ChildMoved {
+                    child: d,
+                    previous_parent: n,
+                    new_parent: a,
+                },
+                ChildRemoved {
+                    child: n,
+                    parent: a,
+                },
+                ChildRemoved {
+                    child: n,
+                    parent: b,
+                },
+            ],
+        );
+
+        world.entity_mut(c).push_children(&[n]);
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, world).entity(c).splice_out(true);
+        queue.apply(world);
+        assert!(world.get_entity(c).is_none());
+        assert_parents(world, n, &[a, b]);
+        assert_children(world, a, &[n, d]);
+    }
+
+    #[test]
+    fn move_child() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        world.entity_mut(a).push_children(&[c]);
+        omit_events(world, 1);
+
+        world.entity_mut(a).move_child(b, c);
+        assert!(world.get::<Children>(a).is_none());
+        assert_children(world, b, &[c]);
+        assert_parents(world, c, &[b]);
+        assert_events(
+            world,
+            &[ChildMoved {
+                child: c,
+                previous_parent: a,
+                new_parent: b,
+            }],
+        );
+
+        // Moving an entity which isn't a child of `self` only adds it to the new parent.
+        world.entity_mut(a).move_child(b, d);
+        assert_children(world, b, &[c, d]);
+        assert_parents(world, d, &[b]);
+        assert_events(
+            world,
+            &[ChildAdded {
+                child: d,
+                parent: b,
+            }],
+        );
+
+        // Moving a shared child to a parent it already has only removes the old edge.
+        world.entity_mut(a).push_children(&[c]);
+        omit_events(world, 1);
+        world.entity_mut(a).move_child(b, c);
+        assert!(world.get::<Children>(a).is_none());
+        assert_parents(world, c, &[b]);
+        assert_events(
+            world,
+            &[ChildRemoved {
+                child: c,
+                parent: a,
+            }],
+        );
+    }
+
+    #[test]
+    fn move_children() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        world.entity_mut(a).push_children(&[c, d]);
+        omit_events(world, 2);
+
+        world.entity_mut(a).move_children(b, &[c, d]);
+        assert!(world.get::<Children>(a).is_none());
+        assert_children(world, b, &[c, d]);
+        assert_parents(world, c, &[b]);
+        assert_parents(world, d, &[b]);
+        assert_events(
+            world,
+            &[
+                ChildMoved {
+                    child: c,
+                    previous_parent: a,
+                    new_parent: b,
+                },
+                ChildMoved {
+                    child: d,
+                    previous_parent: a,
+                    new_parent: b,
+                },
+            ],
+        );
+
+        // Moving to the same parent does nothing.
+        world.entity_mut(b).move_children(b, &[c, d]);
+        assert_children(world, b, &[c, d]);
+        assert_events(world, &[]);
+    }
+
+    #[test]
+    fn replace_children_events() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        world.entity_mut(a).push_children(&[b, c]);
+        omit_events(world, 2);
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, world)
+            .entity(a)
+            .replace_children(&[c, d]);
+        queue.apply(world);
+
+        assert_children(world, a, &[c, d]);
+        assert!(world.get::<Parents>(b).is_none());
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: b,
+                    parent: a,
+                },
+                ChildAdded {
+                    child: d,
+                    parent: a,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn clear() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        world.entity_mut(a).push_children(&[b]);
+        world.entity_mut(b).push_children(&[c, d]);
+        omit_events(world, 3);
+
+        world.entity_mut(b).clear();
+
+        assert!(world.get_entity(b).is_none());
+        assert!(world.get::<Children>(a).is_none());
+        assert!(world.get::<Parents>(c).is_none());
+        assert!(world.get::<Parents>(d).is_none());
+        assert_events(
+            world,
+            &[
+                ChildRemoved {
+                    child: c,
+                    parent: b,
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: b,
+                },
+                ChildRemoved {
+                    child: b,
+                    parent: a,
+                },
+                SubtreeDespawned {
+                    root: b,
+                    entities: vec![b],
+                },
+            ],
+        );
     }
 
     #[derive(Component)]
@@ -984,6 +2217,7 @@ mod tests {
                 .collect::<Vec<_>>(),
             children,
         );
+        assert_eq!(world.get::<C>(children[2]).unwrap().0, 4);
         assert_eq!(
             *world.get::<Parents>(children[0]).unwrap().first().unwrap(),
             parent
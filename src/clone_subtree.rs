@@ -0,0 +1,430 @@
+use std::{
+    any::TypeId,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use bevy_ecs::{
+    entity::{Entity, EntityMap},
+    reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities},
+    system::{Command, EntityCommands},
+    world::{EntityMut, World},
+};
+use bevy_reflect::{Reflect, TypeRegistry};
+use bevy_utils::tracing::{debug, warn};
+
+use crate::{
+    hierarchy::collect_subtree, BuildWorldChildren, Children, DescendantsChanged, HierarchyChanged,
+    Parents,
+};
+
+/// What to do with edges between the cloned subtree and entities outside of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutsideEdges {
+    /// Give each copy the same parents outside of the subtree as its original.
+    Keep,
+    /// Only connect the copies among themselves.
+    #[default]
+    Drop,
+}
+
+/// Command that clones an entity and all its descendants, preserving shared children.
+///
+/// See [`clone_subtree`] for details.
+#[derive(Debug)]
+pub struct CloneSubtree {
+    /// Root of the subtree to clone.
+    pub source: Entity,
+    /// Entity receiving the copy of `source`.
+    pub destination: Entity,
+    /// What to do with edges from parents outside of the subtree.
+    pub outside_edges: OutsideEdges,
+}
+
+impl Command for CloneSubtree {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "command",
+            name = "CloneSubtree",
+            entity = bevy_utils::tracing::field::debug(self.source)
+        )
+        .entered();
+        clone_subtree_into(world, self.source, self.destination, self.outside_edges);
+    }
+}
+
+/// Function for cloning an entity and all its descendants.
+///
+/// Every entity of the subtree is cloned once, so an entity reached through several parents
+/// gets a single copy with the copies of all those parents. Components are cloned through
+/// reflection, using the [`AppTypeRegistry`] resource: components that are not registered with
+/// [`ReflectComponent`] are left out, and only the hierarchy is cloned if the resource is missing.
+/// Entity references inside components registered with [`ReflectMapEntities`] are remapped to
+/// the copies when they point into the subtree, and left unchanged otherwise.
+///
+/// Sends a [`HierarchyEvent::ChildAdded`] per edge of the copy.
+/// Returns the copy of each entity of the subtree, or `None` if `root` does not exist.
+///
+/// [`HierarchyEvent::ChildAdded`]: crate::HierarchyEvent::ChildAdded
+pub fn clone_subtree(
+    world: &mut World,
+    root: Entity,
+    outside_edges: OutsideEdges,
+) -> Option<BTreeMap<Entity, Entity>> {
+    world.get_entity(root)?;
+    let destination = world.spawn_empty().id();
+    Some(clone_subtree_into(world, root, destination, outside_edges))
+}
+
+fn clone_subtree_into(
+    world: &mut World,
+    root: Entity,
+    destination: Entity,
+    outside_edges: OutsideEdges,
+) -> BTreeMap<Entity, Entity> {
+    if world.get_entity(root).is_none() {
+        debug!("Failed to clone entity {:?}", root);
+        world.despawn(destination);
+        return BTreeMap::new();
+    }
+    let mut entities = collect_subtree(world, root);
+    // A plain despawn can leave children behind in `Children`.
+    entities.retain(|entity| world.get_entity(*entity).is_some());
+    let mut copies = BTreeMap::from([(root, destination)]);
+    for &entity in &entities[1..] {
+        copies.insert(entity, world.spawn_empty().id());
+    }
+
+    let registry = world.get_resource::<AppTypeRegistry>().cloned();
+    if let Some(registry) = registry {
+        let registry = registry.read();
+        for &entity in &entities {
+            let components = reflect_components(world, &registry, entity);
+            insert_reflected(world, &registry, copies[&entity], &components);
+        }
+        let outside = map_copied_entities(world, &registry, &copies);
+        restore_entities(world, &registry, &copies, &outside);
+    } else {
+        warn!(
+            "Cloning the hierarchy of {:?} without its components: no AppTypeRegistry resource",
+            root
+        );
+    }
+
+    let subtree = BTreeSet::from_iter(entities.iter().copied());
+    for &entity in &entities {
+        let children = world
+            .get::<Children>(entity)
+            .into_iter()
+            .flatten()
+            .filter_map(|child| copies.get(child).copied())
+            .collect::<Vec<_>>();
+        if !children.is_empty() {
+            world.entity_mut(copies[&entity]).push_children(&children);
+        }
+    }
+    if outside_edges == OutsideEdges::Keep {
+        for &entity in &entities {
+            let parents = world
+                .get::<Parents>(entity)
+                .into_iter()
+                .flatten()
+                .filter(|parent| !subtree.contains(parent))
+                .copied()
+                .collect::<Vec<_>>();
+            for parent in parents {
+                world.entity_mut(parent).push_children(&[copies[&entity]]);
+            }
+        }
+    }
+    copies
+}
+
+/// Clones the reflectable components of `entity`, leaving out the hierarchy components.
+///
+/// Returns nothing if `entity` does not exist.
+pub(crate) fn reflect_components(
+    world: &World,
+    registry: &TypeRegistry,
+    entity: Entity,
+) -> Vec<Box<dyn Reflect>> {
+    let skipped = [
+        TypeId::of::<Parents>(),
+        TypeId::of::<Children>(),
+        TypeId::of::<HierarchyChanged>(),
+        TypeId::of::<DescendantsChanged>(),
+    ];
+    let Some(entity_ref) = world.get_entity(entity) else {
+        return Vec::new();
+    };
+    entity_ref
+        .archetype()
+        .components()
+        .filter_map(|id| world.components().get_info(id)?.type_id())
+        .filter(|type_id| !skipped.contains(type_id))
+        .filter_map(|type_id| {
+            let reflect_component = registry.get_type_data::<ReflectComponent>(type_id)?;
+            Some(reflect_component.reflect(entity_ref)?.clone_value())
+        })
+        .collect()
+}
+
+/// Inserts components cloned by [`reflect_components`] into `entity`.
+pub(crate) fn insert_reflected(
+    world: &mut World,
+    registry: &TypeRegistry,
+    entity: Entity,
+    components: &[Box<dyn Reflect>],
+) {
+    let mut entity_mut = world.entity_mut(entity);
+    for component in components {
+        let reflect_component = component
+            .get_represented_type_info()
+            .and_then(|info| registry.get_type_data::<ReflectComponent>(info.type_id()));
+        if let Some(reflect_component) = reflect_component {
+            reflect_component.insert(&mut entity_mut, &**component);
+        }
+    }
+}
+
+/// Maps the entity references inside the components of the copies in `copies` from the originals
+/// to their copies, through [`ReflectMapEntities`]. The hierarchy components are left alone.
+///
+/// References to entities outside of `copies` are replaced with dead entities, as when loading a
+/// `DynamicScene`. Returns the entity each of these dead entities replaced.
+pub(crate) fn map_copied_entities(
+    world: &mut World,
+    registry: &TypeRegistry,
+    copies: &BTreeMap<Entity, Entity>,
+) -> BTreeMap<Entity, Entity> {
+    let mut entity_map = EntityMap::default();
+    for (&entity, &copy) in copies {
+        entity_map.insert(entity, copy);
+    }
+    map_reflected_entities(world, registry, copies, &mut entity_map);
+    entity_map
+        .iter()
+        .filter(|(entity, _)| !copies.contains_key(entity))
+        .map(|(entity, dead)| (dead, entity))
+        .collect()
+}
+
+/// Maps the dead entities in the components of the copies in `copies` back to the entities they
+/// replaced in `restore`, as returned by [`map_copied_entities`].
+pub(crate) fn restore_entities(
+    world: &mut World,
+    registry: &TypeRegistry,
+    copies: &BTreeMap<Entity, Entity>,
+    restore: &BTreeMap<Entity, Entity>,
+) {
+    if restore.is_empty() {
+        return;
+    }
+    let mut entity_map = EntityMap::default();
+    for (&dead, &entity) in restore {
+        entity_map.insert(dead, entity);
+    }
+    for &copy in copies.values() {
+        entity_map.insert(copy, copy);
+    }
+    map_reflected_entities(world, registry, copies, &mut entity_map);
+}
+
+/// Maps the entity references inside the components of the copies in `copies` through
+/// `entity_map`, skipping the hierarchy components.
+fn map_reflected_entities(
+    world: &mut World,
+    registry: &TypeRegistry,
+    copies: &BTreeMap<Entity, Entity>,
+    entity_map: &mut EntityMap,
+) {
+    let skipped = [TypeId::of::<Parents>(), TypeId::of::<Children>()];
+    let targets = copies.values().copied().collect::<Vec<_>>();
+    for map in registry
+        .iter()
+        .filter(|registration| !skipped.contains(&registration.type_id()))
+        .filter_map(|registration| registration.data::<ReflectMapEntities>())
+    {
+        map.map_entities(world, entity_map, &targets);
+    }
+}
+
+/// Trait that holds functions for cloning an entity and its descendants.
+pub trait CloneRecursiveExt {
+    /// Clones this entity and all its descendants, and returns the copy of this entity.
+    ///
+    /// See [`clone_subtree`] for details. With [`EntityCommands`], the returned entity is
+    /// despawned if this entity no longer exists when the command is applied.
+    fn clone_recursive(&mut self, outside_edges: OutsideEdges) -> Entity;
+}
+
+impl<'w, 's, 'a> CloneRecursiveExt for EntityCommands<'w, 's, 'a> {
+    fn clone_recursive(&mut self, outside_edges: OutsideEdges) -> Entity {
+        let source = self.id();
+        let destination = self.commands().spawn_empty().id();
+        self.commands().add(CloneSubtree {
+            source,
+            destination,
+            outside_edges,
+        });
+        destination
+    }
+}
+
+impl<'w> CloneRecursiveExt for EntityMut<'w> {
+    fn clone_recursive(&mut self, outside_edges: OutsideEdges) -> Entity {
+        let source = self.id();
+
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "clone_recursive",
+            entity = bevy_utils::tracing::field::debug(source)
+        )
+        .entered();
+
+        self.world_scope(|world| {
+            let destination = world.spawn_empty().id();
+            clone_subtree_into(world, source, destination, outside_edges);
+            destination
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        component::Component,
+        entity::{Entity, EntityMapper, MapEntities},
+        reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities},
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+    use bevy_reflect::Reflect;
+
+    use super::{CloneRecursiveExt, OutsideEdges};
+    use crate::{assert_hierarchy_eq, hierarchy::collect_subtree, HierarchySpec};
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Value(u32);
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component, MapEntities)]
+    struct Link(Entity);
+
+    impl Default for Link {
+        fn default() -> Self {
+            Link(Entity::PLACEHOLDER)
+        }
+    }
+
+    impl MapEntities for Link {
+        fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+            self.0 = entity_mapper.get_or_reserve(self.0);
+        }
+    }
+
+    #[test]
+    fn clone_recursive() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Value>();
+
+        let spec: HierarchySpec = "a -> b, c; b, c, x -> d".parse().unwrap();
+        let mut entities = spec.spawn(&mut world);
+        for (index, entity) in entities.values().enumerate() {
+            world.entity_mut(*entity).insert(Value(index as u32));
+        }
+
+        let copy = world
+            .entity_mut(entities["a"])
+            .clone_recursive(OutsideEdges::Drop);
+        let copies = collect_subtree(&world, copy);
+        assert_eq!(copies.len(), 4);
+        for (name, copy) in ["a2", "b2", "d2", "c2"].into_iter().zip(copies) {
+            entities.insert(name.to_owned(), copy);
+        }
+        assert_hierarchy_eq!(
+            world,
+            entities,
+            "a -> b, c; b, c, x -> d; a2 -> b2, c2; b2, c2 -> d2"
+        );
+        assert_eq!(world.get::<Value>(entities["d2"]), Some(&Value(3)));
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let copy = commands
+            .entity(entities["b"])
+            .clone_recursive(OutsideEdges::Keep);
+        queue.apply(&mut world);
+
+        let copies = collect_subtree(&world, copy);
+        entities.insert("b3".to_owned(), copies[0]);
+        entities.insert("d3".to_owned(), copies[1]);
+        assert_hierarchy_eq!(
+            world,
+            entities,
+            "a -> b, b3, c; b, c, x -> d; a2 -> b2, c2; b2, c2 -> d2; b3 -> d3; c, x -> d3"
+        );
+    }
+
+    #[test]
+    fn clone_entity_references() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Link>();
+
+        let spec: HierarchySpec = "a -> b; x".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        world.entity_mut(entities["a"]).insert(Link(entities["b"]));
+        world.entity_mut(entities["b"]).insert(Link(entities["x"]));
+
+        let copy = world
+            .entity_mut(entities["a"])
+            .clone_recursive(OutsideEdges::Drop);
+        let copies = collect_subtree(&world, copy);
+        // References into the subtree point to the copies, others are kept.
+        assert_eq!(world.get::<Link>(copies[0]), Some(&Link(copies[1])));
+        assert_eq!(world.get::<Link>(copies[1]), Some(&Link(entities["x"])));
+        assert_eq!(world.get::<Link>(entities["a"]), Some(&Link(entities["b"])));
+    }
+
+    #[test]
+    fn clone_dangling_child() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Value>();
+
+        let spec: HierarchySpec = "a -> b, c".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        world.entity_mut(entities["c"]).insert(Value(2));
+        // A plain despawn leaves `b` in the children of `a`.
+        world.despawn(entities["b"]);
+
+        let copy = world
+            .entity_mut(entities["a"])
+            .clone_recursive(OutsideEdges::Drop);
+        let copies = collect_subtree(&world, copy);
+        assert_eq!(copies.len(), 2);
+        assert_eq!(world.get::<Value>(copies[1]), Some(&Value(2)));
+
+        // Cloning an entity despawned before the command is applied leaves nothing behind.
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.entity(entities["c"]).despawn();
+        let copy = commands
+            .entity(entities["c"])
+            .clone_recursive(OutsideEdges::Drop);
+        queue.apply(&mut world);
+        assert!(world.get_entity(copy).is_none());
+    }
+}
@@ -1,4 +1,15 @@
-use bevy_ecs::{component::Component, entity::Entity, prelude::FromWorld, world::World};
+use bevy_ecs::{
+    component::Component,
+    entity::{Entity, EntityMapper, MapEntities},
+    prelude::FromWorld,
+    reflect::{ReflectComponent, ReflectMapEntities},
+    world::World,
+};
+use bevy_reflect::Reflect;
+#[cfg(feature = "serde")]
+use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use std::{
     collections::BTreeSet,
@@ -11,11 +22,19 @@ use std::{
 ///
 /// [`HierarchyQueryExt`]: crate::query_extension::HierarchyQueryExt
 /// [`Query`]: bevy_ecs::system::Query
-#[derive(Component, Debug)]
-
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    not(feature = "serde"),
+    reflect_value(Component, MapEntities, Debug, PartialEq)
+)]
+#[cfg_attr(
+    feature = "serde",
+    reflect_value(Component, MapEntities, Debug, PartialEq, Serialize, Deserialize)
+)]
 pub struct Children(pub(crate) BTreeSet<Entity>);
 
-// TODO: We need to impl either FromWorld or Default so Children can be registered as Reflect.
+// We need to impl either FromWorld or Default so Children can be registered as Reflect.
 // This is because Reflect deserialize by creating an instance and apply a patch on top.
 // However Children should only ever be set with a real user-defined entities. Its worth looking
 // into better ways to handle cases like this.
@@ -25,6 +44,16 @@ impl FromWorld for Children {
     }
 }
 
+impl MapEntities for Children {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.0 = self
+            .0
+            .iter()
+            .map(|entity| entity_mapper.get_or_reserve(*entity))
+            .collect();
+    }
+}
+
 impl Children {
     /// Constructs a [`Children`] component with the given entities.
     pub(crate) fn new(entities: BTreeSet<Entity>) -> Self {
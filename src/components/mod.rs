@@ -3,3 +3,109 @@ mod parents;
 
 pub use children::Children;
 pub use parents::Parents;
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        entity::EntityMap,
+        reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities},
+        world::World,
+    };
+    use bevy_reflect::TypeRegistration;
+    #[cfg(feature = "serde")]
+    use bevy_scene::{serde::SceneDeserializer, DynamicScene};
+    #[cfg(feature = "serde")]
+    use serde::de::DeserializeSeed;
+
+    use super::{Children, Parents};
+    use crate::BuildWorldChildren;
+
+    #[test]
+    fn copy_hierarchy_between_worlds() {
+        let source = &mut World::new();
+        let [a, b, c] = std::array::from_fn(|_| source.spawn_empty().id());
+        // a -> b, c; b -> c
+        source.entity_mut(a).push_children(&[b, c]);
+        source.entity_mut(b).push_children(&[c]);
+
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<Children>();
+            registry.register::<Parents>();
+        }
+
+        let destination = &mut World::new();
+        // Offset the ids so that an unmapped entity would be noticed.
+        destination.spawn_empty();
+        let mut entity_map = EntityMap::default();
+        for entity in [a, b, c] {
+            entity_map.insert(entity, destination.spawn_empty().id());
+        }
+
+        let registry = registry.read();
+        let registrations = [
+            registry.get(std::any::TypeId::of::<Children>()).unwrap(),
+            registry.get(std::any::TypeId::of::<Parents>()).unwrap(),
+        ];
+        for registration in registrations {
+            let reflect_component = registration.data::<ReflectComponent>().unwrap();
+            for (from, to) in entity_map.iter() {
+                if reflect_component.reflect(source.entity(from)).is_some() {
+                    reflect_component.copy(source, destination, from, to);
+                }
+            }
+        }
+        for registration in registrations.map(TypeRegistration::data::<ReflectMapEntities>) {
+            registration
+                .unwrap()
+                .map_all_entities(destination, &mut entity_map);
+        }
+
+        let [a, b, c] = [a, b, c].map(|entity| entity_map.get(entity).unwrap());
+        assert_eq!(destination.get::<Children>(a).unwrap().to_vec(), [b, c]);
+        assert_eq!(destination.get::<Children>(b).unwrap().to_vec(), [c]);
+        assert_eq!(destination.get::<Parents>(b).unwrap().to_vec(), [a]);
+        assert_eq!(destination.get::<Parents>(c).unwrap().to_vec(), [a, b]);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn dynamic_scene_round_trip() {
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<Children>();
+            registry.register::<Parents>();
+        }
+
+        let source = &mut World::new();
+        source.insert_resource(registry.clone());
+        let [a, b, c] = std::array::from_fn(|_| source.spawn_empty().id());
+        // a -> b, c; b -> c
+        source.entity_mut(a).push_children(&[b, c]);
+        source.entity_mut(b).push_children(&[c]);
+
+        let ron = DynamicScene::from_world(source)
+            .serialize_ron(&registry)
+            .unwrap();
+        let scene = SceneDeserializer {
+            type_registry: &registry.read(),
+        }
+        .deserialize(&mut ron::de::Deserializer::from_str(&ron).unwrap())
+        .unwrap();
+
+        let destination = &mut World::new();
+        destination.insert_resource(registry);
+        // Offset the ids so that an unmapped entity would be noticed.
+        destination.spawn_empty();
+        let mut entity_map = EntityMap::default();
+        scene.write_to_world(destination, &mut entity_map).unwrap();
+
+        let [a, b, c] = [a, b, c].map(|entity| entity_map.get(entity).unwrap());
+        assert_eq!(destination.get::<Children>(a).unwrap().to_vec(), [b, c]);
+        assert_eq!(destination.get::<Children>(b).unwrap().to_vec(), [c]);
+        assert_eq!(destination.get::<Parents>(b).unwrap().to_vec(), [a]);
+        assert_eq!(destination.get::<Parents>(c).unwrap().to_vec(), [a, b]);
+    }
+}
@@ -1,5 +1,16 @@
 //!  clone from [super::children]
-use bevy_ecs::{component::Component, entity::Entity, prelude::FromWorld, world::World};
+use bevy_ecs::{
+    component::Component,
+    entity::{Entity, EntityMapper, MapEntities},
+    prelude::FromWorld,
+    reflect::{ReflectComponent, ReflectMapEntities},
+    world::World,
+};
+use bevy_reflect::Reflect;
+#[cfg(feature = "serde")]
+use bevy_reflect::{ReflectDeserialize, ReflectSerialize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 use std::{
     collections::BTreeSet,
@@ -12,11 +23,19 @@ use std::{
 ///
 /// [`HierarchyQueryExt`]: crate::query_extension::HierarchyQueryExt
 /// [`Query`]: bevy_ecs::system::Query
-#[derive(Component, Debug)]
-
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    not(feature = "serde"),
+    reflect_value(Component, MapEntities, Debug, PartialEq)
+)]
+#[cfg_attr(
+    feature = "serde",
+    reflect_value(Component, MapEntities, Debug, PartialEq, Serialize, Deserialize)
+)]
 pub struct Parents(pub(crate) BTreeSet<Entity>);
 
-// TODO: We need to impl either FromWorld or Default so Parents can be registered as Reflect.
+// We need to impl either FromWorld or Default so Parents can be registered as Reflect.
 // This is because Reflect deserialize by creating an instance and apply a patch on top.
 // However Parents should only ever be set with a real user-defined entities. Its worth looking
 // into better ways to handle cases like this.
@@ -26,6 +45,16 @@ impl FromWorld for Parents {
     }
 }
 
+impl MapEntities for Parents {
+    fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+        self.0 = self
+            .0
+            .iter()
+            .map(|entity| entity_mapper.get_or_reserve(*entity))
+            .collect();
+    }
+}
+
 impl Parents {
     /// Constructs a [`Parents`] component with the given entities.
     pub(crate) fn new(entities: BTreeSet<Entity>) -> Self {
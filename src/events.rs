@@ -28,4 +28,13 @@ pub enum HierarchyEvent {
         /// The parent the child was added to
         new_parent: Entity,
     },
+    /// Fired whenever an [`Entity`] and its descendants are despawned recursively.
+    ///
+    /// Sent after the [`HierarchyEvent::ChildRemoved`] events of every edge that was severed.
+    SubtreeDespawned {
+        /// The entity the despawn was requested on
+        root: Entity,
+        /// The despawned entities. Contains `root` unless only its descendants were despawned.
+        entities: Vec<Entity>,
+    },
 }
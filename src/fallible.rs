@@ -0,0 +1,822 @@
+use std::{collections::BTreeSet, fmt, ops::Deref};
+
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    system::{Command, EntityCommands},
+    world::{EntityMut, EntityRef, World},
+};
+use bevy_utils::tracing::warn;
+
+use crate::{
+    merge_nodes, AddChild, AddParents, BuildWorldChildren, Children, Clear, ClearChildren,
+    IntersectChildren, Isolate, MergeConflicts, MergeNodes, MoveChild, MoveChildren, Parents,
+    PushChildren, RemoveChildren, RemoveParent, RemoveParents, Reparent, ReplaceChildren,
+    ReplaceParents, RetainChildren, SpliceOut, TransferChildren, UnionChildren,
+};
+
+/// An error returned by the fallible hierarchy functions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyError {
+    /// The entity does not exist, for example because it was despawned earlier in the frame.
+    MissingEntity(Entity),
+    /// Adding the edge from `parent` to `child` would close a cycle, or make an entity its own
+    /// parent.
+    Cycle {
+        /// The parent of the rejected edge.
+        parent: Entity,
+        /// The child of the rejected edge.
+        child: Entity,
+    },
+}
+
+impl fmt::Display for HierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HierarchyError::MissingEntity(entity) => write!(f, "entity {entity:?} does not exist"),
+            HierarchyError::Cycle { parent, child } => {
+                write!(
+                    f,
+                    "making {child:?} a child of {parent:?} would create a cycle"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HierarchyError {}
+
+/// Returns the first of `entities` that does not exist in `world`.
+pub(crate) fn check_entities(world: &World, entities: &[Entity]) -> Result<(), HierarchyError> {
+    match entities
+        .iter()
+        .find(|entity| world.get_entity(**entity).is_none())
+    {
+        Some(&entity) => Err(HierarchyError::MissingEntity(entity)),
+        None => Ok(()),
+    }
+}
+
+/// Returns the first entity in the `C` edges of `entity` that does not exist in `world`, as left
+/// behind by a plain [`World::despawn`].
+fn check_edges<C>(world: &World, entity: Entity) -> Result<(), HierarchyError>
+where
+    C: Component + Deref<Target = BTreeSet<Entity>>,
+{
+    match world
+        .get::<C>(entity)
+        .into_iter()
+        .flat_map(|edges| edges.iter())
+        .find(|entity| world.get_entity(**entity).is_none())
+    {
+        Some(&entity) => Err(HierarchyError::MissingEntity(entity)),
+        None => Ok(()),
+    }
+}
+
+/// Command that applies `command` only if all of `entities` exist, and logs a warning otherwise.
+#[derive(Debug)]
+pub struct TryCommand<C> {
+    /// Entities that must exist for `command` to be applied.
+    pub entities: Vec<Entity>,
+    /// Entities whose parents must exist too, because `command` edits their edges.
+    pub parents_of: Vec<Entity>,
+    /// Entities whose children must exist too, because `command` edits their edges.
+    pub children_of: Vec<Entity>,
+    /// The command to apply.
+    pub command: C,
+}
+
+impl<C> TryCommand<C> {
+    fn check(&self, world: &World) -> Result<(), HierarchyError> {
+        check_entities(world, &self.entities)?;
+        for &entity in &self.parents_of {
+            check_edges::<Parents>(world, entity)?;
+        }
+        for &entity in &self.children_of {
+            check_edges::<Children>(world, entity)?;
+        }
+        Ok(())
+    }
+}
+
+impl<C: Command> Command for TryCommand<C> {
+    fn apply(self, world: &mut World) {
+        match self.check(world) {
+            Ok(()) => self.command.apply(world),
+            Err(error) => warn!(
+                "Skipping hierarchy command {}: {error}",
+                std::any::type_name::<C>()
+            ),
+        }
+    }
+}
+
+/// Trait for editing the hierarchy with [`Commands`] without panicking on despawned entities.
+///
+/// Each function queues the same command as its [`BuildChildren`] counterpart, which is skipped
+/// with a warning if one of the entities it refers to no longer exists when it is applied. This
+/// includes the current parents or children whose edges the command edits, which a plain
+/// [`World::despawn`] can leave behind.
+///
+/// [`Commands`]: bevy_ecs::system::Commands
+/// [`BuildChildren`]: crate::BuildChildren
+pub trait TryBuildChildren {
+    /// Fallible [`BuildChildren::push_children`](crate::BuildChildren::push_children).
+    fn try_push_children(&mut self, children: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::move_children`](crate::BuildChildren::move_children).
+    fn try_move_children(&mut self, new_parent: Entity, children: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::remove_children`](crate::BuildChildren::remove_children).
+    fn try_remove_children(&mut self, children: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::add_child`](crate::BuildChildren::add_child).
+    fn try_add_child(&mut self, child: Entity) -> &mut Self;
+    /// Fallible [`BuildChildren::move_child`](crate::BuildChildren::move_child).
+    fn try_move_child(&mut self, new_parent: Entity, child: Entity) -> &mut Self;
+    /// Fallible [`BuildChildren::clear_children`](crate::BuildChildren::clear_children).
+    fn try_clear_children(&mut self) -> &mut Self;
+    /// Fallible [`BuildChildren::replace_children`](crate::BuildChildren::replace_children).
+    fn try_replace_children(&mut self, children: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::retain_children`](crate::BuildChildren::retain_children).
+    fn try_retain_children<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: FnMut(EntityRef) -> bool + Send + 'static;
+    /// Fallible [`BuildChildren::union_children_from`](crate::BuildChildren::union_children_from).
+    fn try_union_children_from(&mut self, other: Entity) -> &mut Self;
+    /// Fallible
+    /// [`BuildChildren::intersect_children_with`](crate::BuildChildren::intersect_children_with).
+    fn try_intersect_children_with(&mut self, children: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::transfer_children`](crate::BuildChildren::transfer_children).
+    fn try_transfer_children(&mut self, to: Entity) -> &mut Self;
+    /// Fallible [`BuildChildren::set_parent`](crate::BuildChildren::set_parent).
+    fn try_set_parent(&mut self, parent: Entity) -> &mut Self;
+    /// Fallible [`BuildChildren::remove_parent`](crate::BuildChildren::remove_parent).
+    fn try_remove_parent(&mut self, parent: Entity) -> &mut Self;
+    /// Fallible [`BuildChildren::add_parents`](crate::BuildChildren::add_parents).
+    fn try_add_parents(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::replace_parents`](crate::BuildChildren::replace_parents).
+    fn try_replace_parents(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::clear_parents`](crate::BuildChildren::clear_parents).
+    fn try_clear_parents(&mut self) -> &mut Self;
+    /// Fallible [`BuildChildren::reparent`](crate::BuildChildren::reparent).
+    fn try_reparent(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::reparent_checked`](crate::BuildChildren::reparent_checked).
+    fn try_reparent_checked(&mut self, parents: &[Entity]) -> &mut Self;
+    /// Fallible [`BuildChildren::isolate`](crate::BuildChildren::isolate).
+    fn try_isolate(&mut self) -> &mut Self;
+    /// Same as `try_isolate`.
+    fn try_detach_all(&mut self) -> &mut Self;
+    /// Fallible [`BuildChildren::clear`](crate::BuildChildren::clear).
+    fn try_clear(self);
+    /// Fallible [`BuildChildren::splice_out`](crate::BuildChildren::splice_out).
+    fn try_splice_out(self, despawn: bool);
+    /// Fallible [`BuildChildren::merge_from`](crate::BuildChildren::merge_from).
+    fn try_merge_from(&mut self, source: Entity, conflicts: MergeConflicts) -> &mut Self;
+}
+
+fn add_try<'c, 'w, 's, 'a, C: Command>(
+    entity_commands: &'c mut EntityCommands<'w, 's, 'a>,
+    entities: &[Entity],
+    command: C,
+) -> &'c mut EntityCommands<'w, 's, 'a> {
+    add_try_with_edges(entity_commands, entities, &[], &[], command)
+}
+
+fn add_try_with_edges<'c, 'w, 's, 'a, C: Command>(
+    entity_commands: &'c mut EntityCommands<'w, 's, 'a>,
+    entities: &[Entity],
+    parents_of: &[Entity],
+    children_of: &[Entity],
+    command: C,
+) -> &'c mut EntityCommands<'w, 's, 'a> {
+    let mut entities = entities.to_vec();
+    entities.push(entity_commands.id());
+    entity_commands.commands().add(TryCommand {
+        entities,
+        parents_of: parents_of.to_vec(),
+        children_of: children_of.to_vec(),
+        command,
+    });
+    entity_commands
+}
+
+impl<'w, 's, 'a> TryBuildChildren for EntityCommands<'w, 's, 'a> {
+    fn try_push_children(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        add_try(
+            self,
+            children,
+            PushChildren {
+                parent,
+                children: Vec::from(children),
+            },
+        )
+    }
+
+    fn try_move_children(&mut self, new_parent: Entity, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        let mut entities = Vec::from(children);
+        entities.push(new_parent);
+        add_try(
+            self,
+            &entities,
+            MoveChildren {
+                parent,
+                children: Vec::from(children),
+                new_parent,
+            },
+        )
+    }
+
+    fn try_remove_children(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        add_try(
+            self,
+            children,
+            RemoveChildren {
+                parent,
+                children: Vec::from(children),
+            },
+        )
+    }
+
+    fn try_add_child(&mut self, child: Entity) -> &mut Self {
+        let parent = self.id();
+        add_try(self, &[child], AddChild { parent, child })
+    }
+
+    fn try_move_child(&mut self, new_parent: Entity, child: Entity) -> &mut Self {
+        let parent = self.id();
+        add_try(
+            self,
+            &[child, new_parent],
+            MoveChild {
+                parent,
+                child,
+                new_parent,
+            },
+        )
+    }
+
+    fn try_clear_children(&mut self) -> &mut Self {
+        let parent = self.id();
+        add_try_with_edges(self, &[], &[], &[parent], ClearChildren { parent })
+    }
+
+    fn try_replace_children(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        add_try_with_edges(
+            self,
+            children,
+            &[],
+            &[parent],
+            ReplaceChildren {
+                parent,
+                children: Vec::from(children),
+            },
+        )
+    }
+
+    fn try_retain_children<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: FnMut(EntityRef) -> bool + Send + 'static,
+    {
+        let parent = self.id();
+        add_try(self, &[], RetainChildren { parent, predicate })
+    }
+
+    fn try_union_children_from(&mut self, other: Entity) -> &mut Self {
+        let parent = self.id();
+        add_try_with_edges(
+            self,
+            &[other],
+            &[],
+            &[other],
+            UnionChildren { parent, other },
+        )
+    }
+
+    fn try_intersect_children_with(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        add_try_with_edges(
+            self,
+            &[],
+            &[],
+            &[parent],
+            IntersectChildren {
+                parent,
+                children: Vec::from(children),
+            },
+        )
+    }
+
+    fn try_transfer_children(&mut self, to: Entity) -> &mut Self {
+        let from = self.id();
+        add_try_with_edges(self, &[to], &[], &[from], TransferChildren { from, to })
+    }
+
+    fn try_set_parent(&mut self, parent: Entity) -> &mut Self {
+        let child = self.id();
+        add_try(self, &[parent], AddChild { parent, child })
+    }
+
+    fn try_remove_parent(&mut self, parent: Entity) -> &mut Self {
+        let child = self.id();
+        add_try(self, &[parent], RemoveParent { child, parent })
+    }
+
+    fn try_add_parents(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        add_try(
+            self,
+            parents,
+            AddParents {
+                child,
+                parents: Vec::from(parents),
+            },
+        )
+    }
+
+    fn try_replace_parents(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        add_try_with_edges(
+            self,
+            parents,
+            &[child],
+            &[],
+            ReplaceParents {
+                child,
+                parents: Vec::from(parents),
+            },
+        )
+    }
+
+    fn try_clear_parents(&mut self) -> &mut Self {
+        let child = self.id();
+        add_try_with_edges(self, &[], &[child], &[], RemoveParents { child })
+    }
+
+    fn try_reparent(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        add_try_with_edges(
+            self,
+            parents,
+            &[child],
+            &[],
+            Reparent {
+                child,
+                parents: Vec::from(parents),
+                check_cycles: false,
+            },
+        )
+    }
+
+    fn try_reparent_checked(&mut self, parents: &[Entity]) -> &mut Self {
+        let child = self.id();
+        add_try_with_edges(
+            self,
+            parents,
+            &[child],
+            &[],
+            Reparent {
+                child,
+                parents: Vec::from(parents),
+                check_cycles: true,
+            },
+        )
+    }
+
+    fn try_isolate(&mut self) -> &mut Self {
+        let entity = self.id();
+        add_try_with_edges(self, &[], &[entity], &[entity], Isolate { entity })
+    }
+
+    fn try_detach_all(&mut self) -> &mut Self {
+        self.try_isolate()
+    }
+
+    fn try_clear(mut self) {
+        let entity = self.id();
+        add_try_with_edges(&mut self, &[], &[entity], &[entity], Clear { entity });
+    }
+
+    fn try_splice_out(mut self, despawn: bool) {
+        let entity = self.id();
+        add_try_with_edges(
+            &mut self,
+            &[],
+            &[entity],
+            &[entity],
+            SpliceOut { entity, despawn },
+        );
+    }
+
+    fn try_merge_from(&mut self, source: Entity, conflicts: MergeConflicts) -> &mut Self {
+        let target = self.id();
+        add_try_with_edges(
+            self,
+            &[source],
+            &[source],
+            &[source],
+            MergeNodes {
+                target,
+                source,
+                conflicts,
+            },
+        )
+    }
+}
+
+/// Trait for editing the hierarchy through the [`World`] without panicking on despawned entities.
+///
+/// Each function checks that the entities it refers to exist, including the current parents or
+/// children whose edges it edits, which a plain [`World::despawn`] can leave behind. It returns
+/// [`HierarchyError::MissingEntity`] without changing anything otherwise, and then behaves as its
+/// [`BuildWorldChildren`] counterpart. [`BuildWorldChildren::retain_children`] skips missing
+/// children by itself, so it has no fallible version.
+pub trait TryBuildWorldChildren {
+    /// Fallible [`BuildWorldChildren::move_child`].
+    fn try_move_child(
+        &mut self,
+        new_parent: Entity,
+        child: Entity,
+    ) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::add_child`].
+    fn try_add_child(&mut self, child: Entity) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::move_children`].
+    fn try_move_children(
+        &mut self,
+        new_parent: Entity,
+        children: &[Entity],
+    ) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::push_children`].
+    fn try_push_children(&mut self, children: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::remove_children`].
+    fn try_remove_children(&mut self, children: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::clear_children`].
+    fn try_clear_children(&mut self) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::replace_children`].
+    fn try_replace_children(&mut self, children: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::union_children_from`].
+    fn try_union_children_from(&mut self, other: Entity) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::intersect_children_with`].
+    fn try_intersect_children_with(
+        &mut self,
+        children: &[Entity],
+    ) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::transfer_children`].
+    fn try_transfer_children(&mut self, to: Entity) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::set_parent`].
+    fn try_set_parent(&mut self, parent: Entity) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::remove_parent`].
+    fn try_remove_parent(&mut self, parent: Entity) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::add_parents`].
+    fn try_add_parents(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::replace_parents`].
+    fn try_replace_parents(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::clear_parents`].
+    fn try_clear_parents(&mut self) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::reparent`].
+    fn try_reparent(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::reparent_checked`].
+    fn try_reparent_checked(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::isolate`].
+    fn try_isolate(&mut self) -> Result<&mut Self, HierarchyError>;
+    /// Same as `try_isolate`.
+    fn try_detach_all(&mut self) -> Result<&mut Self, HierarchyError>;
+    /// Fallible [`BuildWorldChildren::clear`].
+    fn try_clear(self) -> Result<(), HierarchyError>;
+    /// Fallible [`BuildWorldChildren::splice_out`].
+    fn try_splice_out(self, despawn: bool) -> Result<(), HierarchyError>;
+    /// Fallible [`BuildWorldChildren::merge_from`], returning the error of
+    /// [`merge_nodes`](crate::merge_nodes) instead of logging it.
+    fn try_merge_from(
+        &mut self,
+        source: Entity,
+        conflicts: MergeConflicts,
+    ) -> Result<&mut Self, HierarchyError>;
+}
+
+impl<'w> TryBuildWorldChildren for EntityMut<'w> {
+    fn try_move_child(
+        &mut self,
+        new_parent: Entity,
+        child: Entity,
+    ) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[child, new_parent])?;
+        Ok(self.move_child(new_parent, child))
+    }
+
+    fn try_add_child(&mut self, child: Entity) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[child])?;
+        Ok(self.add_child(child))
+    }
+
+    fn try_move_children(
+        &mut self,
+        new_parent: Entity,
+        children: &[Entity],
+    ) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), children)?;
+        check_entities(self.world(), &[new_parent])?;
+        Ok(self.move_children(new_parent, children))
+    }
+
+    fn try_push_children(&mut self, children: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), children)?;
+        Ok(self.push_children(children))
+    }
+
+    fn try_remove_children(&mut self, children: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), children)?;
+        Ok(self.remove_children(children))
+    }
+
+    fn try_clear_children(&mut self) -> Result<&mut Self, HierarchyError> {
+        check_edges::<Children>(self.world(), self.id())?;
+        Ok(self.clear_children())
+    }
+
+    fn try_replace_children(&mut self, children: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), children)?;
+        check_edges::<Children>(self.world(), self.id())?;
+        Ok(self.replace_children(children))
+    }
+
+    fn try_union_children_from(&mut self, other: Entity) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[other])?;
+        check_edges::<Children>(self.world(), other)?;
+        Ok(self.union_children_from(other))
+    }
+
+    fn try_intersect_children_with(
+        &mut self,
+        children: &[Entity],
+    ) -> Result<&mut Self, HierarchyError> {
+        check_edges::<Children>(self.world(), self.id())?;
+        Ok(self.intersect_children_with(children))
+    }
+
+    fn try_transfer_children(&mut self, to: Entity) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[to])?;
+        check_edges::<Children>(self.world(), self.id())?;
+        Ok(self.transfer_children(to))
+    }
+
+    fn try_set_parent(&mut self, parent: Entity) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[parent])?;
+        Ok(self.set_parent(parent))
+    }
+
+    fn try_remove_parent(&mut self, parent: Entity) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[parent])?;
+        Ok(self.remove_parent(parent))
+    }
+
+    fn try_add_parents(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), parents)?;
+        Ok(self.add_parents(parents))
+    }
+
+    fn try_replace_parents(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), parents)?;
+        check_edges::<Parents>(self.world(), self.id())?;
+        Ok(self.replace_parents(parents))
+    }
+
+    fn try_clear_parents(&mut self) -> Result<&mut Self, HierarchyError> {
+        check_edges::<Parents>(self.world(), self.id())?;
+        Ok(self.clear_parents())
+    }
+
+    fn try_reparent(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), parents)?;
+        check_edges::<Parents>(self.world(), self.id())?;
+        Ok(self.reparent(parents))
+    }
+
+    fn try_reparent_checked(&mut self, parents: &[Entity]) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), parents)?;
+        check_edges::<Parents>(self.world(), self.id())?;
+        self.reparent_checked(parents)
+    }
+
+    fn try_isolate(&mut self) -> Result<&mut Self, HierarchyError> {
+        check_edges::<Parents>(self.world(), self.id())?;
+        check_edges::<Children>(self.world(), self.id())?;
+        Ok(self.isolate())
+    }
+
+    fn try_detach_all(&mut self) -> Result<&mut Self, HierarchyError> {
+        self.try_isolate()
+    }
+
+    fn try_clear(self) -> Result<(), HierarchyError> {
+        check_edges::<Parents>(self.world(), self.id())?;
+        check_edges::<Children>(self.world(), self.id())?;
+        self.clear();
+        Ok(())
+    }
+
+    fn try_splice_out(self, despawn: bool) -> Result<(), HierarchyError> {
+        check_edges::<Parents>(self.world(), self.id())?;
+        check_edges::<Children>(self.world(), self.id())?;
+        self.splice_out(despawn);
+        Ok(())
+    }
+
+    fn try_merge_from(
+        &mut self,
+        source: Entity,
+        conflicts: MergeConflicts,
+    ) -> Result<&mut Self, HierarchyError> {
+        check_entities(self.world(), &[source])?;
+        check_edges::<Parents>(self.world(), source)?;
+        check_edges::<Children>(self.world(), source)?;
+        let target = self.id();
+        self.world_scope(|world| merge_nodes(world, target, source, conflicts))?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+
+    use super::{HierarchyError, TryBuildChildren, TryBuildWorldChildren};
+    use crate::{
+        assert_hierarchy_eq, BuildChildren, Children, HierarchySpec, MergeConflicts, Parents,
+    };
+
+    #[test]
+    fn try_world() {
+        let mut world = World::new();
+        let entities = "a -> b; c"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        let [a, b, c] = ["a", "b", "c"].map(|name| entities[name]);
+        let gone = world.spawn_empty().id();
+        world.despawn(gone);
+
+        assert_eq!(
+            world
+                .entity_mut(a)
+                .try_push_children(&[c, gone])
+                .map(|_| ()),
+            Err(HierarchyError::MissingEntity(gone))
+        );
+        assert_eq!(
+            world.entity_mut(a).try_move_child(gone, b).map(|_| ()),
+            Err(HierarchyError::MissingEntity(gone))
+        );
+        assert_hierarchy_eq!(world, entities, "a -> b; c");
+
+        world
+            .entity_mut(a)
+            .try_move_child(c, b)
+            .unwrap()
+            .try_add_child(c)
+            .unwrap();
+        assert_hierarchy_eq!(world, entities, "a -> c; c -> b");
+
+        assert_eq!(
+            world.entity_mut(b).try_reparent(&[a, gone]).map(|_| ()),
+            Err(HierarchyError::MissingEntity(gone))
+        );
+        assert_eq!(
+            world.entity_mut(c).try_reparent_checked(&[b]).map(|_| ()),
+            Err(HierarchyError::Cycle {
+                parent: b,
+                child: c
+            })
+        );
+        assert_eq!(
+            world
+                .entity_mut(a)
+                .try_merge_from(gone, MergeConflicts::Skip)
+                .map(|_| ()),
+            Err(HierarchyError::MissingEntity(gone))
+        );
+        assert_hierarchy_eq!(world, entities, "a -> c; c -> b");
+
+        world.entity_mut(b).try_reparent(&[a]).unwrap();
+        world.entity_mut(c).try_clear_parents().unwrap();
+        assert_hierarchy_eq!(world, entities, "a -> b; c");
+
+        world
+            .entity_mut(a)
+            .try_merge_from(c, MergeConflicts::Skip)
+            .unwrap();
+        assert!(world.get_entity(c).is_none());
+
+        let d = world.spawn_empty().id();
+        world.entity_mut(a).try_replace_children(&[d]).unwrap();
+        assert_eq!(world.get::<Children>(a).unwrap().to_vec(), [d]);
+        world.entity_mut(a).try_clear_children().unwrap();
+        assert!(world.get::<Children>(a).is_none());
+    }
+
+    #[test]
+    fn dangling_edges() {
+        let mut world = World::new();
+        let entities = "a -> b, c; x -> c"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        let [a, b, c, x] = ["a", "b", "c", "x"].map(|name| entities[name]);
+        // A plain despawn leaves `b` in the children of `a` and `x` in the parents of `c`.
+        world.despawn(b);
+        world.despawn(x);
+
+        let missing_b = Err(HierarchyError::MissingEntity(b));
+        let missing_x = Err(HierarchyError::MissingEntity(x));
+        assert_eq!(
+            world.entity_mut(a).try_clear_children().map(|_| ()),
+            missing_b
+        );
+        assert_eq!(
+            world.entity_mut(a).try_replace_children(&[c]).map(|_| ()),
+            missing_b
+        );
+        assert_eq!(
+            world
+                .entity_mut(a)
+                .try_intersect_children_with(&[c])
+                .map(|_| ()),
+            missing_b
+        );
+        assert_eq!(
+            world.entity_mut(a).try_transfer_children(c).map(|_| ()),
+            missing_b
+        );
+        assert_eq!(
+            world.entity_mut(c).try_union_children_from(a).map(|_| ()),
+            missing_b
+        );
+        assert_eq!(world.entity_mut(a).try_isolate().map(|_| ()), missing_b);
+        assert_eq!(world.entity_mut(a).try_clear(), missing_b);
+        assert_eq!(
+            world.entity_mut(c).try_clear_parents().map(|_| ()),
+            missing_x
+        );
+        assert_eq!(
+            world.entity_mut(c).try_replace_parents(&[a]).map(|_| ()),
+            missing_x
+        );
+        assert_eq!(
+            world.entity_mut(c).try_reparent(&[a]).map(|_| ()),
+            missing_x
+        );
+        assert_eq!(world.entity_mut(c).try_splice_out(false), missing_x);
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.entity(a).try_clear_children();
+        commands.entity(a).try_transfer_children(c);
+        commands.entity(a).try_detach_all();
+        commands.entity(c).try_clear_parents();
+        commands.entity(c).try_reparent_checked(&[a]);
+        commands.entity(c).try_merge_from(a, MergeConflicts::Skip);
+        commands.entity(a).try_clear();
+        queue.apply(&mut world);
+
+        assert_eq!(world.get::<Children>(a).unwrap().to_vec(), [b, c]);
+        assert_eq!(world.get::<Parents>(c).unwrap().to_vec(), [a, x]);
+    }
+
+    #[test]
+    fn try_commands() {
+        let mut world = World::new();
+        let entities = "a -> b; c"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        let [a, b, c] = ["a", "b", "c"].map(|name| entities[name]);
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.entity(c).despawn();
+        commands.entity(a).try_push_children(&[c]);
+        commands.entity(a).try_move_child(c, b);
+        commands.entity(b).try_set_parent(c);
+        commands.entity(c).try_replace_children(&[b]);
+        commands.entity(b).try_reparent(&[c]);
+        commands.entity(a).try_union_children_from(c);
+        commands.entity(c).try_transfer_children(a);
+        commands.entity(b).try_retain_children(|_| false);
+        commands.entity(a).try_merge_from(c, MergeConflicts::Skip);
+        commands.entity(c).try_isolate();
+        commands.entity(c).try_clear();
+        commands.entity(c).try_splice_out(true);
+        // `replace_children` on an entity without children no longer panics.
+        commands.entity(b).replace_children(&[]);
+        queue.apply(&mut world);
+
+        let mut entities = entities;
+        entities.remove("c");
+        assert_hierarchy_eq!(world, entities, "a -> b");
+    }
+}
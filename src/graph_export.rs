@@ -0,0 +1,549 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    fmt::{self, Write},
+};
+
+use bevy_ecs::{
+    entity::Entity,
+    world::{EntityRef, World},
+};
+
+use crate::{hierarchy::collect_hierarchy, Children, Parents};
+
+/// The text format written by [`write_graph`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// A Graphviz DOT `digraph`.
+    #[default]
+    Dot,
+    /// A Mermaid `flowchart`.
+    Mermaid,
+    /// An indented text tree, drawn with box characters.
+    ///
+    /// Each entity is expanded once. Later occurrences are marked `(shared, see above)`,
+    /// or `(cycle)` if the entity is one of its own ancestors, and [`Children`] entries pointing
+    /// to despawned entities are marked `(despawned)`. Without explicit roots, the trees start at
+    /// the entities without [`Parents`]. Highlighting options are ignored.
+    Tree,
+}
+
+/// Options for [`write_graph`].
+#[derive(Default)]
+pub struct GraphExport<'a> {
+    /// The text format to write.
+    pub format: GraphFormat,
+    /// Only export these entities and their descendants.
+    /// When `None`, every entity with a [`Parents`] or [`Children`] component is exported.
+    /// Except in the [`GraphFormat::Tree`] format, despawned entities are left out.
+    pub roots: Option<&'a [Entity]>,
+    /// Computes the label of a node, such as the value of a `Name` component.
+    /// Nodes without a label, or all nodes when `None`, are labelled with their [`Entity`].
+    pub label: Option<&'a dyn Fn(EntityRef) -> Option<String>>,
+    /// Draw the edges that are part of a cycle in red.
+    pub highlight_cycles: bool,
+    /// Fill the nodes that have more than one exported parent.
+    pub highlight_shared: bool,
+}
+
+/// Writes the edges of the hierarchy as a graph in the format given by `options`.
+///
+/// Nodes and edges are written in [`Entity`] order, so the output is stable for a given world
+/// and can be used in snapshot tests.
+///
+/// # Examples
+/// ```
+/// # use bevy_ecs::prelude::*;
+/// # use bevy_parents_childs::{write_graph, BuildWorldChildren, GraphExport, GraphFormat};
+/// let mut world = World::new();
+/// let child = world.spawn_empty().id();
+/// world.spawn_empty().push_children(&[child]);
+///
+/// let mut mermaid = String::new();
+/// let options = GraphExport {
+///     format: GraphFormat::Mermaid,
+///     ..Default::default()
+/// };
+/// write_graph(&world, &options, &mut mermaid).unwrap();
+///
+/// let mut tree = String::new();
+/// let options = GraphExport {
+///     format: GraphFormat::Tree,
+///     ..Default::default()
+/// };
+/// write_graph(&world, &options, &mut tree).unwrap();
+/// bevy_log::debug!("hierarchy:\n{tree}");
+/// ```
+pub fn write_graph(world: &World, options: &GraphExport, out: &mut impl Write) -> fmt::Result {
+    match options.format {
+        GraphFormat::Dot => write_dot(world, options, out),
+        GraphFormat::Mermaid => write_mermaid(world, options, out),
+        GraphFormat::Tree => write_tree(world, options, out),
+    }
+}
+
+/// The nodes and edges written by the DOT and Mermaid formats.
+struct Graph {
+    entities: BTreeSet<Entity>,
+    edges: Vec<(Entity, Entity)>,
+    cyclic: BTreeSet<(Entity, Entity)>,
+    shared: BTreeSet<Entity>,
+}
+
+impl Graph {
+    /// Collects the exported entities and edges. [`Children`] entries pointing to despawned
+    /// entities are left out.
+    fn new(world: &World, options: &GraphExport) -> Self {
+        let mut entities = collect_hierarchy(world, options.roots);
+        entities.retain(|entity| world.get_entity(*entity).is_some());
+
+        let edges = entities
+            .iter()
+            .flat_map(|&parent| {
+                let children = world.get::<Children>(parent).into_iter().flatten();
+                children
+                    .filter(|child| entities.contains(child))
+                    .map(move |&child| (parent, child))
+            })
+            .collect::<Vec<_>>();
+
+        let cyclic = if options.highlight_cycles {
+            cyclic_edges(&entities, &edges)
+        } else {
+            BTreeSet::new()
+        };
+        let shared = if options.highlight_shared {
+            let mut parent_counts = BTreeMap::<Entity, usize>::new();
+            for (_, child) in &edges {
+                *parent_counts.entry(*child).or_default() += 1;
+            }
+            parent_counts
+                .into_iter()
+                .filter(|(_, count)| *count > 1)
+                .map(|(child, _)| child)
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+
+        Graph {
+            entities,
+            edges,
+            cyclic,
+            shared,
+        }
+    }
+}
+
+/// The label of `entity`, or `None` if it does not exist.
+fn node_label(
+    world: &World,
+    label: Option<&dyn Fn(EntityRef) -> Option<String>>,
+    entity: Entity,
+) -> Option<String> {
+    let entity_ref = world.get_entity(entity)?;
+    Some(
+        label
+            .and_then(|label| label(entity_ref))
+            .unwrap_or_else(|| format!("{entity:?}")),
+    )
+}
+
+fn write_dot(world: &World, options: &GraphExport, out: &mut impl Write) -> fmt::Result {
+    let graph = Graph::new(world, options);
+    writeln!(out, "digraph hierarchy {{")?;
+    for &entity in &graph.entities {
+        let style = if graph.shared.contains(&entity) {
+            ", style=filled, fillcolor=lightblue"
+        } else {
+            ""
+        };
+        let label = node_label(world, options.label, entity)
+            .unwrap_or_default()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        writeln!(out, "    \"{entity:?}\" [label=\"{label}\"{style}];")?;
+    }
+    for edge @ (parent, child) in &graph.edges {
+        let style = if graph.cyclic.contains(edge) {
+            " [color=red]"
+        } else {
+            ""
+        };
+        writeln!(out, "    \"{parent:?}\" -> \"{child:?}\"{style};")?;
+    }
+    writeln!(out, "}}")
+}
+
+fn write_mermaid(world: &World, options: &GraphExport, out: &mut impl Write) -> fmt::Result {
+    let graph = Graph::new(world, options);
+    writeln!(out, "flowchart TD")?;
+    for &entity in &graph.entities {
+        let label = node_label(world, options.label, entity)
+            .unwrap_or_default()
+            .replace('"', "#quot;");
+        writeln!(out, "    e{entity:?}[\"{label}\"]")?;
+    }
+    for (parent, child) in &graph.edges {
+        writeln!(out, "    e{parent:?} --> e{child:?}")?;
+    }
+    for entity in &graph.shared {
+        writeln!(out, "    style e{entity:?} fill:lightblue")?;
+    }
+    for (index, edge) in graph.edges.iter().enumerate() {
+        if graph.cyclic.contains(edge) {
+            writeln!(out, "    linkStyle {index} stroke:red")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_tree(world: &World, options: &GraphExport, out: &mut impl Write) -> fmt::Result {
+    let mut tree = TreeWriter {
+        world,
+        label: options.label,
+        printed: BTreeSet::new(),
+        path: BTreeSet::new(),
+    };
+
+    match options.roots {
+        Some(roots) => {
+            for &root in roots {
+                if world.get_entity(root).is_some() {
+                    tree.write_root(root, out)?;
+                }
+            }
+        }
+        None => {
+            let entities = world
+                .iter_entities()
+                .filter(|entity| entity.contains::<Parents>() || entity.contains::<Children>())
+                .map(|entity| (entity.id(), entity.contains::<Parents>()))
+                .collect::<BTreeMap<_, _>>();
+            for (&entity, _) in entities.iter().filter(|(_, has_parents)| !**has_parents) {
+                tree.write_root(entity, out)?;
+            }
+            // Entities only reachable through a cycle have no root without parents.
+            for &entity in entities.keys() {
+                if !tree.printed.contains(&entity) {
+                    tree.write_root(entity, out)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+struct TreeWriter<'w, 'a> {
+    world: &'w World,
+    label: Option<&'a dyn Fn(EntityRef) -> Option<String>>,
+    /// Entities that were already expanded.
+    printed: BTreeSet<Entity>,
+    /// Ancestors of the entity being written.
+    path: BTreeSet<Entity>,
+}
+
+/// A pending step of [`TreeWriter::write_root`].
+enum TreeStep {
+    /// Write `entity` after `prefix`, then its children after `children_prefix`.
+    Enter {
+        entity: Entity,
+        prefix: String,
+        children_prefix: String,
+    },
+    /// All children of `entity` were written.
+    Exit(Entity),
+}
+
+impl TreeWriter<'_, '_> {
+    /// Writes the tree under `root` depth-first, with an explicit stack so that deep hierarchies
+    /// can't overflow the call stack.
+    fn write_root(&mut self, root: Entity, out: &mut impl Write) -> fmt::Result {
+        let mut stack = vec![TreeStep::Enter {
+            entity: root,
+            prefix: String::new(),
+            children_prefix: String::new(),
+        }];
+        while let Some(step) = stack.pop() {
+            let (entity, prefix, children_prefix) = match step {
+                TreeStep::Enter {
+                    entity,
+                    prefix,
+                    children_prefix,
+                } => (entity, prefix, children_prefix),
+                TreeStep::Exit(entity) => {
+                    self.path.remove(&entity);
+                    continue;
+                }
+            };
+
+            let Some(label) = node_label(self.world, self.label, entity) else {
+                writeln!(out, "{prefix}{entity:?} (despawned)")?;
+                continue;
+            };
+            if self.path.contains(&entity) {
+                writeln!(out, "{prefix}{label} (cycle)")?;
+                continue;
+            }
+            if !self.printed.insert(entity) {
+                writeln!(out, "{prefix}{label} (shared, see above)")?;
+                continue;
+            }
+            writeln!(out, "{prefix}{label}")?;
+
+            let children = self
+                .world
+                .get::<Children>(entity)
+                .map(|children| children.to_vec())
+                .unwrap_or_default();
+            self.path.insert(entity);
+            stack.push(TreeStep::Exit(entity));
+            for (index, &child) in children.iter().enumerate().rev() {
+                let (branch, indent) = if index + 1 == children.len() {
+                    ("└── ", "    ")
+                } else {
+                    ("├── ", "│   ")
+                };
+                stack.push(TreeStep::Enter {
+                    entity: child,
+                    prefix: format!("{children_prefix}{branch}"),
+                    children_prefix: format!("{children_prefix}{indent}"),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the edges whose endpoints lie in the same strongly connected component,
+/// which are exactly the edges that are part of a cycle.
+fn cyclic_edges(
+    entities: &BTreeSet<Entity>,
+    edges: &[(Entity, Entity)],
+) -> BTreeSet<(Entity, Entity)> {
+    let mut forward = BTreeMap::<Entity, Vec<Entity>>::new();
+    let mut backward = BTreeMap::<Entity, Vec<Entity>>::new();
+    for &(parent, child) in edges {
+        forward.entry(parent).or_default().push(child);
+        backward.entry(child).or_default().push(parent);
+    }
+
+    // Kosaraju: order nodes by depth-first finishing time on the forward edges.
+    let mut visited = BTreeSet::new();
+    let mut finished = Vec::with_capacity(entities.len());
+    for &start in entities {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut stack = vec![(start, 0)];
+        while let Some((entity, next)) = stack.pop() {
+            let neighbors = forward.get(&entity).map_or(&[][..], Vec::as_slice);
+            if let Some(&neighbor) = neighbors.get(next) {
+                stack.push((entity, next + 1));
+                if visited.insert(neighbor) {
+                    stack.push((neighbor, 0));
+                }
+            } else {
+                finished.push(entity);
+            }
+        }
+    }
+
+    // Then collect components on the backward edges, in reverse finishing order.
+    let mut components = BTreeMap::new();
+    for (component, &start) in finished.iter().rev().enumerate() {
+        if components.contains_key(&start) {
+            continue;
+        }
+        components.insert(start, component);
+        let mut stack = vec![start];
+        while let Some(entity) = stack.pop() {
+            for &neighbor in backward.get(&entity).into_iter().flatten() {
+                if let Entry::Vacant(entry) = components.entry(neighbor) {
+                    entry.insert(component);
+                    stack.push(neighbor);
+                }
+            }
+        }
+    }
+
+    edges
+        .iter()
+        .filter(|(parent, child)| components.get(parent) == components.get(child))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::{self, Write};
+
+    use bevy_ecs::{component::Component, world::World};
+
+    use super::{write_graph, GraphExport, GraphFormat};
+    use crate::BuildWorldChildren;
+
+    #[derive(Component)]
+    struct Name(&'static str);
+
+    #[test]
+    fn write_dot_and_mermaid() {
+        let world = &mut World::new();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| world.spawn(Name(name)).id());
+        world.spawn_empty();
+
+        // a -> b, c; b -> d; c -> d; d -> c
+        world.entity_mut(a).push_children(&[b, c]);
+        world.entity_mut(b).push_children(&[d]);
+        world.entity_mut(c).push_children(&[d]);
+        world.entity_mut(d).push_children(&[c]);
+
+        let label = |entity: bevy_ecs::world::EntityRef| {
+            entity.get::<Name>().map(|name| format!("\"{}\"", name.0))
+        };
+        let mut options = GraphExport {
+            roots: Some(&[b]),
+            label: Some(&label),
+            highlight_cycles: true,
+            highlight_shared: true,
+            ..Default::default()
+        };
+
+        let mut dot = String::new();
+        write_graph(world, &options, &mut dot).unwrap();
+        assert_eq!(
+            dot,
+            r#"digraph hierarchy {
+    "1v0" [label="\"b\""];
+    "2v0" [label="\"c\""];
+    "3v0" [label="\"d\"", style=filled, fillcolor=lightblue];
+    "1v0" -> "3v0";
+    "2v0" -> "3v0" [color=red];
+    "3v0" -> "2v0" [color=red];
+}
+"#
+        );
+
+        options.format = GraphFormat::Mermaid;
+        options.roots = None;
+        options.label = None;
+        options.highlight_shared = false;
+        let mut mermaid = String::new();
+        write_graph(world, &options, &mut mermaid).unwrap();
+        assert_eq!(
+            mermaid,
+            r#"flowchart TD
+    e0v0["0v0"]
+    e1v0["1v0"]
+    e2v0["2v0"]
+    e3v0["3v0"]
+    e0v0 --> e1v0
+    e0v0 --> e2v0
+    e1v0 --> e3v0
+    e2v0 --> e3v0
+    e3v0 --> e2v0
+    linkStyle 3 stroke:red
+    linkStyle 4 stroke:red
+"#
+        );
+    }
+
+    #[test]
+    fn write_tree() {
+        let world = &mut World::new();
+        let [a, b, c, d, e] = ["a", "b", "c", "d", "e"].map(|name| world.spawn(Name(name)).id());
+        let [f, g] = ["f", "g"].map(|name| world.spawn(Name(name)).id());
+
+        // a -> b, c; b -> d; c -> d, e; e -> c; f -> g; g -> f
+        world.entity_mut(a).push_children(&[b, c]);
+        world.entity_mut(b).push_children(&[d]);
+        world.entity_mut(c).push_children(&[d, e]);
+        world.entity_mut(e).push_children(&[c]);
+        world.entity_mut(f).push_children(&[g]);
+        world.entity_mut(g).push_children(&[f]);
+
+        let label =
+            |entity: bevy_ecs::world::EntityRef| entity.get::<Name>().map(|name| name.0.to_owned());
+        let options = GraphExport {
+            format: GraphFormat::Tree,
+            label: Some(&label),
+            ..Default::default()
+        };
+
+        let mut tree = String::new();
+        write_graph(world, &options, &mut tree).unwrap();
+        assert_eq!(
+            tree,
+            "\
+a
+├── b
+│   └── d
+└── c
+    ├── d (shared, see above)
+    └── e
+        └── c (cycle)
+f
+└── g
+    └── f (cycle)
+"
+        );
+    }
+
+    #[test]
+    fn despawned_children() {
+        let world = &mut World::new();
+        let [a, b, c] = std::array::from_fn(|_| world.spawn_empty().id());
+        world.entity_mut(a).push_children(&[b, c]);
+        // Leaves a dangling entry in `a`'s `Children`.
+        world.despawn(b);
+
+        let mut mermaid = String::new();
+        let options = GraphExport {
+            format: GraphFormat::Mermaid,
+            ..Default::default()
+        };
+        write_graph(world, &options, &mut mermaid).unwrap();
+        assert_eq!(
+            mermaid,
+            "flowchart TD\n    e0v0[\"0v0\"]\n    e2v0[\"2v0\"]\n    e0v0 --> e2v0\n"
+        );
+
+        let mut tree = String::new();
+        let options = GraphExport {
+            format: GraphFormat::Tree,
+            roots: Some(&[a]),
+            ..Default::default()
+        };
+        write_graph(world, &options, &mut tree).unwrap();
+        assert_eq!(tree, "0v0\n├── 1v0 (despawned)\n└── 2v0\n");
+    }
+
+    #[test]
+    fn write_deep_tree() {
+        /// Counts the lines instead of keeping them.
+        struct Lines(usize);
+        impl Write for Lines {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0 += s.matches('\n').count();
+                Ok(())
+            }
+        }
+
+        let world = &mut World::new();
+        let root = world.spawn_empty().id();
+        let mut parent = root;
+        for _ in 0..10_000 {
+            let child = world.spawn_empty().id();
+            world.entity_mut(parent).push_children(&[child]);
+            parent = child;
+        }
+
+        let mut lines = Lines(0);
+        let options = GraphExport {
+            format: GraphFormat::Tree,
+            roots: Some(&[root]),
+            ..Default::default()
+        };
+        write_graph(world, &options, &mut lines).unwrap();
+        assert_eq!(lines.0, 10_001);
+    }
+}
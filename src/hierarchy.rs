@@ -1,4 +1,11 @@
-use crate::{components::Children, remove_children_unidirectional, Parents};
+use std::collections::BTreeSet;
+
+use crate::{
+    child_builder::{push_events, remove_children_unidirectional, remove_parent_unidirectional},
+    components::Children,
+    journal::journal_despawn,
+    HierarchyEvent, Parents,
+};
 use bevy_ecs::{
     entity::Entity,
     system::{Command, EntityCommands},
@@ -21,36 +28,119 @@ pub struct DespawnChildrenRecursive {
 }
 
 /// Function for despawning an entity and all its children
+///
+/// Sends a [`HierarchyEvent::ChildRemoved`] for every severed edge, followed by a
+/// [`HierarchyEvent::SubtreeDespawned`].
 pub fn despawn_with_children_recursive(world: &mut World, entity: Entity) {
-    if let Some(parents) = world.get::<Parents>(entity).map(|e| e.0.clone()) {
-        for parent in parents {
-            remove_children_unidirectional(world, &[entity], parent);
-        }
+    if world.get_entity(entity).is_none() {
+        debug!("Failed to despawn entity {:?}", entity);
+        return;
     }
+    let entities = collect_subtree(world, entity);
+    despawn_entities(world, entity, entities);
+}
 
-    // then despawn the entity and all of its children
-    despawn_with_children_recursive_inner(world, entity);
+fn despawn_children_recursive(world: &mut World, entity: Entity) {
+    let mut entities = collect_subtree(world, entity);
+    // A cycle may lead back to `entity`, which must survive.
+    entities.retain(|e| *e != entity);
+    despawn_entities(world, entity, entities);
+}
+
+/// Returns `entity` followed by all of its descendants, each visited once, in depth-first order.
+pub(crate) fn collect_subtree(world: &World, entity: Entity) -> Vec<Entity> {
+    collect_subtree_until(world, entity, |_| false)
 }
 
-// Should only be called by `despawn_with_children_recursive`!
-fn despawn_with_children_recursive_inner(world: &mut World, entity: Entity) {
-    if let Some(mut children) = world.get_mut::<Children>(entity) {
-        for e in std::mem::take(&mut children.0) {
-            despawn_with_children_recursive_inner(world, e);
+/// Same as [`collect_subtree`], but leaves out descendants for which `stop` returns true, along
+/// with everything only reachable through them.
+pub(crate) fn collect_subtree_until(
+    world: &World,
+    entity: Entity,
+    stop: impl Fn(Entity) -> bool,
+) -> Vec<Entity> {
+    let mut visited = BTreeSet::from([entity]);
+    let mut entities = Vec::new();
+    let mut stack = vec![entity];
+    while let Some(e) = stack.pop() {
+        entities.push(e);
+        if let Some(children) = world.get::<Children>(e) {
+            for &child in children.iter().rev() {
+                if !stop(child) && visited.insert(child) {
+                    stack.push(child);
+                }
+            }
         }
     }
+    entities
+}
 
-    if !world.despawn(entity) {
-        debug!("Failed to despawn entity {:?}", entity);
+/// Returns `roots` and all of their descendants, or when `roots` is `None`, every entity with a
+/// [`Parents`] or [`Children`] component.
+pub(crate) fn collect_hierarchy(world: &World, roots: Option<&[Entity]>) -> BTreeSet<Entity> {
+    match roots {
+        Some(roots) => roots
+            .iter()
+            .filter(|root| world.get_entity(**root).is_some())
+            .flat_map(|root| collect_subtree(world, *root))
+            .collect(),
+        None => world
+            .iter_entities()
+            .filter(|entity| entity.contains::<Parents>() || entity.contains::<Children>())
+            .map(|entity| entity.id())
+            .collect(),
     }
 }
 
-fn despawn_children_recursive(world: &mut World, entity: Entity) {
-    if let Some(children) = world.entity_mut(entity).take::<Children>() {
-        for e in children.0 {
-            despawn_with_children_recursive_inner(world, e);
+// Should only be called with the output of `collect_subtree`!
+pub(crate) fn despawn_entities(world: &mut World, root: Entity, entities: Vec<Entity>) {
+    if entities.is_empty() {
+        return;
+    }
+    let despawned = BTreeSet::from_iter(entities.iter().copied());
+
+    // Every edge into the subtree is recorded in the child's `Parents`.
+    // Edges from outside parents must also be removed from their `Children`.
+    let mut events = Vec::new();
+    for &entity in &entities {
+        let Some(parents) = world.get::<Parents>(entity).map(|p| p.to_vec()) else {
+            continue;
+        };
+        for parent in parents {
+            if !despawned.contains(&parent) {
+                remove_children_unidirectional(world, &[entity], parent);
+            }
+            events.push(HierarchyEvent::ChildRemoved {
+                child: entity,
+                parent,
+            });
+        }
+    }
+    // Only reachable through a cycle back to a surviving root.
+    for &entity in &entities {
+        let Some(children) = world.get::<Children>(entity).map(|c| c.to_vec()) else {
+            continue;
+        };
+        for child in children {
+            if !despawned.contains(&child) {
+                remove_parent_unidirectional(world, child, entity);
+                events.push(HierarchyEvent::ChildRemoved {
+                    child,
+                    parent: entity,
+                });
+            }
         }
     }
+
+    journal_despawn(world, &entities);
+    for &entity in &entities {
+        if !world.despawn(entity) {
+            debug!("Failed to despawn entity {:?}", entity);
+        }
+    }
+
+    events.push(HierarchyEvent::SubtreeDespawned { root, entities });
+    push_events(world, events);
 }
 
 impl Command for DespawnRecursive {
@@ -138,12 +228,17 @@ impl<'w> DespawnRecursiveExt for EntityMut<'w> {
 mod tests {
     use bevy_ecs::{
         component::Component,
+        event::Events,
         system::{CommandQueue, Commands},
         world::World,
     };
 
     use super::DespawnRecursiveExt;
-    use crate::{child_builder::BuildChildren, components::Children};
+    use crate::{
+        child_builder::{BuildChildren, BuildWorldChildren},
+        components::{Children, Parents},
+        HierarchyEvent::{self, ChildRemoved, SubtreeDespawned},
+    };
 
     #[derive(Component, Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Debug)]
     struct Idx(u32);
@@ -280,4 +375,109 @@ mod tests {
         // The original child should be despawned.
         assert!(world.get_entity(child).is_none());
     }
+
+    #[test]
+    fn despawn_recursive_events() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d, outside] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        // a -> b, c; b -> d; c -> d; outside -> d
+        world.entity_mut(a).push_children(&[b, c]);
+        world.entity_mut(b).push_children(&[d]);
+        world.entity_mut(c).push_children(&[d]);
+        world.entity_mut(outside).push_children(&[d]);
+        world.resource_mut::<Events<HierarchyEvent>>().clear();
+
+        world.entity_mut(a).despawn_recursive();
+
+        for entity in [a, b, c, d] {
+            assert!(world.get_entity(entity).is_none());
+        }
+        assert!(
+            world.get::<Children>(outside).is_none(),
+            "outside parent should no longer know about the despawned shared child"
+        );
+
+        let events: Vec<_> = world
+            .resource_mut::<Events<HierarchyEvent>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ChildRemoved {
+                    child: b,
+                    parent: a,
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: b
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: c
+                },
+                ChildRemoved {
+                    child: d,
+                    parent: outside,
+                },
+                ChildRemoved {
+                    child: c,
+                    parent: a,
+                },
+                SubtreeDespawned {
+                    root: a,
+                    entities: vec![a, b, d, c],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn despawn_descendants_events() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        // a -> b -> c -> a
+        world.entity_mut(a).push_children(&[b]);
+        world.entity_mut(b).push_children(&[c]);
+        world.entity_mut(c).push_children(&[a]);
+        world.resource_mut::<Events<HierarchyEvent>>().clear();
+
+        world.entity_mut(a).despawn_descendants();
+
+        assert!(world.get_entity(a).is_some());
+        assert!(world.get::<Children>(a).is_none());
+        assert!(world.get::<Parents>(a).is_none());
+
+        let events: Vec<_> = world
+            .resource_mut::<Events<HierarchyEvent>>()
+            .drain()
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ChildRemoved {
+                    child: b,
+                    parent: a,
+                },
+                ChildRemoved {
+                    child: c,
+                    parent: b
+                },
+                ChildRemoved {
+                    child: a,
+                    parent: c,
+                },
+                SubtreeDespawned {
+                    root: a,
+                    entities: vec![b, c],
+                },
+            ]
+        );
+    }
 }
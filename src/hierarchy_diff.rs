@@ -0,0 +1,425 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use bevy_ecs::{entity::Entity, world::World};
+
+use crate::{hierarchy::collect_hierarchy, BuildWorldChildren, Children};
+
+/// The edges of a hierarchy at some point in time, to compute a [`HierarchyDiff`] against later.
+///
+/// Unlike a `HierarchySnapshot`, this keeps the [`Entity`] ids of the world it was taken from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EdgeSnapshot {
+    roots: Option<Vec<Entity>>,
+    entities: BTreeSet<Entity>,
+    edges: BTreeSet<(Entity, Entity)>,
+}
+
+impl EdgeSnapshot {
+    /// Takes a snapshot of every entity with a [`Parents`] or [`Children`] component.
+    ///
+    /// [`Parents`]: crate::Parents
+    pub fn from_world(world: &World) -> Self {
+        Self::take(world, None)
+    }
+
+    /// Takes a snapshot of `roots` and all of their descendants.
+    ///
+    /// Edges from parents outside of the subgraph are left out.
+    pub fn from_roots(world: &World, roots: &[Entity]) -> Self {
+        Self::take(world, Some(roots.to_vec()))
+    }
+
+    fn take(world: &World, roots: Option<Vec<Entity>>) -> Self {
+        let entities = collect_hierarchy(world, roots.as_deref());
+        let mut edges = BTreeSet::new();
+        for &parent in &entities {
+            for &child in world.get::<Children>(parent).into_iter().flatten() {
+                if entities.contains(&child) {
+                    edges.insert((parent, child));
+                }
+            }
+        }
+        EdgeSnapshot {
+            roots,
+            entities,
+            edges,
+        }
+    }
+
+    /// The entities of the snapshot.
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// The `(parent, child)` pairs of the snapshot, sorted.
+    pub fn edges(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.edges.iter().copied()
+    }
+
+    /// Computes the changes from this snapshot to `other`.
+    ///
+    /// Every entity that is only part of `other` is reported as [`HierarchyChange::NodeAdded`],
+    /// and every entity that is only part of this snapshot as [`HierarchyChange::NodeRemoved`].
+    /// Use [`EdgeSnapshot::diff_world`] to leave out entities that left the hierarchy but are still
+    /// alive.
+    pub fn diff(&self, other: &EdgeSnapshot) -> HierarchyDiff {
+        self.diff_with(other, |_| false)
+    }
+
+    /// Computes the changes from this snapshot to the current state of `world`.
+    ///
+    /// The current state is taken the same way as this snapshot, from the same roots if any.
+    /// Entities that left the hierarchy without being despawned only show up through their edges,
+    /// [`HierarchyChange::NodeRemoved`] is reserved for entities that no longer exist in `world`.
+    pub fn diff_world(&self, world: &World) -> HierarchyDiff {
+        self.diff_with(&Self::take(world, self.roots.clone()), |entity| {
+            world.get_entity(entity).is_some()
+        })
+    }
+
+    fn diff_with(&self, other: &EdgeSnapshot, alive: impl Fn(Entity) -> bool) -> HierarchyDiff {
+        let mut changes = Vec::new();
+        for &entity in other.entities.difference(&self.entities) {
+            changes.push(HierarchyChange::NodeAdded(entity));
+        }
+
+        let mut removed = self
+            .edges
+            .difference(&other.edges)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut added = other
+            .edges
+            .difference(&self.edges)
+            .copied()
+            .collect::<Vec<_>>();
+        // A child that lost exactly one parent and gained exactly one was moved.
+        let count = |edges: &[(Entity, Entity)], child: Entity| {
+            edges.iter().filter(|edge| edge.1 == child).count()
+        };
+        let moved = removed
+            .iter()
+            .filter(|&&(_, child)| {
+                other.entities.contains(&child)
+                    && count(&removed, child) == 1
+                    && count(&added, child) == 1
+            })
+            .map(|&(_, child)| child)
+            .collect::<BTreeSet<_>>();
+        let mut moves = Vec::new();
+        for &child in &moved {
+            let previous_parent = removed.iter().find(|edge| edge.1 == child).unwrap().0;
+            let new_parent = added.iter().find(|edge| edge.1 == child).unwrap().0;
+            moves.push(HierarchyChange::ChildMoved {
+                child,
+                previous_parent,
+                new_parent,
+            });
+        }
+        removed.retain(|edge| !moved.contains(&edge.1));
+        added.retain(|edge| !moved.contains(&edge.1));
+
+        changes.extend(
+            removed
+                .into_iter()
+                .map(|(parent, child)| HierarchyChange::EdgeRemoved { parent, child }),
+        );
+        changes.extend(moves);
+        changes.extend(
+            added
+                .into_iter()
+                .map(|(parent, child)| HierarchyChange::EdgeAdded { parent, child }),
+        );
+        for &entity in self.entities.difference(&other.entities) {
+            if !alive(entity) {
+                changes.push(HierarchyChange::NodeRemoved(entity));
+            }
+        }
+        HierarchyDiff { changes }
+    }
+}
+
+/// A single change of a [`HierarchyDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyChange {
+    /// An entity that is part of the second snapshot but not of the first.
+    NodeAdded(Entity),
+    /// An edge that was removed, other than by a [`HierarchyChange::ChildMoved`].
+    EdgeRemoved {
+        /// The parent the child was removed from
+        parent: Entity,
+        /// The child that was removed
+        child: Entity,
+    },
+    /// A child that lost exactly one parent and gained exactly one.
+    ChildMoved {
+        /// The child that was moved
+        child: Entity,
+        /// The parent the child was removed from
+        previous_parent: Entity,
+        /// The parent the child was added to
+        new_parent: Entity,
+    },
+    /// An edge that was added, other than by a [`HierarchyChange::ChildMoved`].
+    EdgeAdded {
+        /// The parent the child was added to
+        parent: Entity,
+        /// The child that was added
+        child: Entity,
+    },
+    /// An entity that is part of the first snapshot but not of the second.
+    ///
+    /// [`EdgeSnapshot::diff_world`] only reports entities that were despawned.
+    NodeRemoved(Entity),
+}
+
+impl fmt::Display for HierarchyChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HierarchyChange::NodeAdded(entity) => write!(f, "+ {entity:?}"),
+            HierarchyChange::EdgeRemoved { parent, child } => {
+                write!(f, "- {parent:?} -> {child:?}")
+            }
+            HierarchyChange::ChildMoved {
+                child,
+                previous_parent,
+                new_parent,
+            } => write!(f, "~ {child:?}: {previous_parent:?} => {new_parent:?}"),
+            HierarchyChange::EdgeAdded { parent, child } => {
+                write!(f, "+ {parent:?} -> {child:?}")
+            }
+            HierarchyChange::NodeRemoved(entity) => write!(f, "- {entity:?}"),
+        }
+    }
+}
+
+/// The changes between two [`EdgeSnapshot`]s, as returned by [`EdgeSnapshot::diff`].
+///
+/// Changes are ordered so that they can be applied in sequence: added nodes first, then removed
+/// edges, moves, added edges, and removed nodes last. Each group is sorted by [`Entity`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HierarchyDiff {
+    /// The changes, in the order they should be applied.
+    pub changes: Vec<HierarchyChange>,
+}
+
+impl fmt::Display for HierarchyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl HierarchyDiff {
+    /// Returns `true` if the snapshots had the same entities and edges.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Applies the changes to `world` through [`BuildWorldChildren`], sending the matching
+    /// [`HierarchyEvent`]s.
+    ///
+    /// `entities` maps the entities of the diff to the entities of `world`. Entities missing from
+    /// it are used as they are, so an empty map applies the diff to the world it was computed
+    /// from. Each [`HierarchyChange::NodeAdded`] spawns a fresh entity and records it in
+    /// `entities`, and each [`HierarchyChange::NodeRemoved`] despawns its entity as by
+    /// [`BuildWorldChildren::clear`]. Changes referring to entities that do not exist in `world`
+    /// are skipped.
+    ///
+    /// [`HierarchyEvent`]: crate::HierarchyEvent
+    pub fn apply(&self, world: &mut World, entities: &mut BTreeMap<Entity, Entity>) {
+        let get = |entities: &BTreeMap<Entity, Entity>, world: &World, entity: Entity| {
+            let entity = entities.get(&entity).copied().unwrap_or(entity);
+            world.get_entity(entity).map(|_| entity)
+        };
+        for change in &self.changes {
+            match *change {
+                HierarchyChange::NodeAdded(entity) => {
+                    entities.insert(entity, world.spawn_empty().id());
+                }
+                HierarchyChange::EdgeRemoved { parent, child } => {
+                    if let (Some(parent), Some(child)) =
+                        (get(entities, world, parent), get(entities, world, child))
+                    {
+                        world.entity_mut(parent).remove_children(&[child]);
+                    }
+                }
+                HierarchyChange::ChildMoved {
+                    child,
+                    previous_parent,
+                    new_parent,
+                } => {
+                    if let (Some(child), Some(previous_parent), Some(new_parent)) = (
+                        get(entities, world, child),
+                        get(entities, world, previous_parent),
+                        get(entities, world, new_parent),
+                    ) {
+                        world
+                            .entity_mut(previous_parent)
+                            .move_child(new_parent, child);
+                    }
+                }
+                HierarchyChange::EdgeAdded { parent, child } => {
+                    if let (Some(parent), Some(child)) =
+                        (get(entities, world, parent), get(entities, world, child))
+                    {
+                        world.entity_mut(parent).push_children(&[child]);
+                    }
+                }
+                HierarchyChange::NodeRemoved(entity) => {
+                    if let Some(entity) = get(entities, world, entity) {
+                        world.entity_mut(entity).clear();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bevy_ecs::world::World;
+
+    use super::{EdgeSnapshot, HierarchyChange};
+    use crate::{assert_hierarchy_eq, BuildWorldChildren, DespawnRecursiveExt, HierarchySpec};
+
+    #[test]
+    fn diff_and_apply() {
+        let spec: HierarchySpec = "a -> b, c; b, c -> d; e -> f".parse().unwrap();
+        let mut world = World::new();
+        let entities = spec.spawn(&mut world);
+        let mut other = World::new();
+        let other_entities = spec.spawn(&mut other);
+
+        let before = EdgeSnapshot::from_world(&world);
+        assert!(before.diff_world(&world).is_empty());
+
+        world
+            .entity_mut(entities["a"])
+            .move_child(entities["e"], entities["c"]);
+        world
+            .entity_mut(entities["b"])
+            .remove_children(&[entities["d"]]);
+        world.entity_mut(entities["f"]).despawn_recursive();
+        let g = world.spawn_empty().id();
+        world.entity_mut(entities["a"]).push_children(&[g]);
+
+        let diff = before.diff_world(&world);
+        assert_eq!(
+            diff.changes,
+            [
+                HierarchyChange::NodeAdded(g),
+                HierarchyChange::EdgeRemoved {
+                    parent: entities["b"],
+                    child: entities["d"],
+                },
+                HierarchyChange::EdgeRemoved {
+                    parent: entities["e"],
+                    child: entities["f"],
+                },
+                HierarchyChange::ChildMoved {
+                    child: entities["c"],
+                    previous_parent: entities["a"],
+                    new_parent: entities["e"],
+                },
+                HierarchyChange::EdgeAdded {
+                    parent: entities["a"],
+                    child: g,
+                },
+                HierarchyChange::NodeRemoved(entities["f"]),
+            ]
+        );
+        assert_eq!(
+            diff.changes[3].to_string(),
+            format!(
+                "~ {:?}: {:?} => {:?}",
+                entities["c"], entities["a"], entities["e"]
+            )
+        );
+
+        let mut mapping = spec
+            .nodes()
+            .iter()
+            .map(|name| (entities[name], other_entities[name]))
+            .collect::<BTreeMap<_, _>>();
+        diff.apply(&mut other, &mut mapping);
+        let mut other_entities = other_entities;
+        other_entities.remove("f");
+        other_entities.insert("g".to_owned(), mapping[&g]);
+        assert!(other.get_entity(mapping[&entities["f"]]).is_none());
+        assert_hierarchy_eq!(other, other_entities, "a -> b, g; c -> d; e -> c");
+    }
+
+    #[test]
+    fn diff_subtree() {
+        let mut world = World::new();
+        let spec: HierarchySpec = "a -> b; b -> c; x -> y".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+
+        let before = EdgeSnapshot::from_roots(&world, &[entities["a"]]);
+        assert_eq!(before.entities().count(), 3);
+        world
+            .entity_mut(entities["x"])
+            .push_children(&[entities["c"]]);
+        world
+            .entity_mut(entities["b"])
+            .remove_children(&[entities["c"]]);
+
+        // `c` left the subtree but is still alive, so only its edge is reported.
+        let diff = before.diff_world(&world);
+        assert_eq!(
+            diff.to_string(),
+            format!("- {:?} -> {:?}\n", entities["b"], entities["c"])
+        );
+    }
+
+    #[test]
+    fn detached_node_survives() {
+        let mut world = World::new();
+        let spec: HierarchySpec = "a -> b; b -> c".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+
+        let before = EdgeSnapshot::from_world(&world);
+        world
+            .entity_mut(entities["b"])
+            .remove_parent(entities["a"])
+            .remove_children(&[entities["c"]]);
+        let diff = before.diff_world(&world);
+        assert_eq!(
+            diff.changes,
+            [
+                HierarchyChange::EdgeRemoved {
+                    parent: entities["a"],
+                    child: entities["b"],
+                },
+                HierarchyChange::EdgeRemoved {
+                    parent: entities["b"],
+                    child: entities["c"],
+                },
+            ]
+        );
+        // Without the world, `b` leaving the hierarchy can't be told apart from a despawn.
+        assert!(before
+            .diff(&EdgeSnapshot::from_world(&world))
+            .changes
+            .contains(&HierarchyChange::NodeRemoved(entities["b"])));
+
+        let mut other = World::new();
+        let other_entities = spec.spawn(&mut other);
+        let mut mapping = spec
+            .nodes()
+            .iter()
+            .map(|name| (entities[name], other_entities[name]))
+            .collect::<BTreeMap<_, _>>();
+        diff.apply(&mut other, &mut mapping);
+        assert!(other.get_entity(other_entities["b"]).is_some());
+        assert_hierarchy_eq!(other, other_entities, "a; b; c");
+    }
+}
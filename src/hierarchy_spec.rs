@@ -0,0 +1,273 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+use bevy_ecs::{entity::Entity, world::World};
+
+use crate::{BuildWorldChildren, Children, Parents};
+
+/// A hierarchy described in a small text format, for building and checking graphs in tests.
+///
+/// Statements are separated by `;` or new lines. Each statement is a chain of comma-separated
+/// node names joined by `->`, where every node on the left is a parent of every node on the
+/// right. A statement with a single name declares a node without edges.
+///
+/// ```
+/// # use bevy_ecs::prelude::*;
+/// # use bevy_parents_childs::{assert_hierarchy_eq, HierarchySpec};
+/// let mut world = World::new();
+/// let spec: HierarchySpec = "a -> b, c; c -> d; e -> d".parse().unwrap();
+/// let entities = spec.spawn(&mut world);
+///
+/// assert_hierarchy_eq!(world, entities, "a -> b, c; c, e -> d");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HierarchySpec {
+    nodes: Vec<String>,
+    edges: BTreeSet<(String, String)>,
+}
+
+/// An error returned when parsing a [`HierarchySpec`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseHierarchySpecError {
+    /// The statement that could not be parsed.
+    pub statement: String,
+}
+
+impl fmt::Display for ParseHierarchySpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "empty node name in statement `{}`", self.statement)
+    }
+}
+
+impl std::error::Error for ParseHierarchySpecError {}
+
+impl FromStr for HierarchySpec {
+    type Err = ParseHierarchySpecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut spec = HierarchySpec::default();
+        for statement in s.split([';', '\n']).map(str::trim) {
+            if statement.is_empty() {
+                continue;
+            }
+            let mut previous: Vec<&str> = Vec::new();
+            for segment in statement.split("->") {
+                let names = segment.split(',').map(str::trim).collect::<Vec<_>>();
+                if names.iter().any(|name| name.is_empty()) {
+                    return Err(ParseHierarchySpecError {
+                        statement: statement.to_owned(),
+                    });
+                }
+                for name in &names {
+                    if !spec.nodes.iter().any(|node| node == name) {
+                        spec.nodes.push((*name).to_owned());
+                    }
+                }
+                for parent in &previous {
+                    for child in &names {
+                        spec.edges
+                            .insert(((*parent).to_owned(), (*child).to_owned()));
+                    }
+                }
+                previous = names;
+            }
+        }
+        Ok(spec)
+    }
+}
+
+impl HierarchySpec {
+    /// The node names, in order of first appearance.
+    pub fn nodes(&self) -> &[String] {
+        &self.nodes
+    }
+
+    /// The `(parent, child)` name pairs, sorted.
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.edges
+            .iter()
+            .map(|(parent, child)| (parent.as_str(), child.as_str()))
+    }
+
+    /// Spawns one entity per node, in order of first appearance, and connects them.
+    ///
+    /// Returns the entity spawned for each name.
+    pub fn spawn(&self, world: &mut World) -> BTreeMap<String, Entity> {
+        let entities = self
+            .nodes
+            .iter()
+            .map(|name| (name.clone(), world.spawn_empty().id()))
+            .collect::<BTreeMap<_, _>>();
+        for name in &self.nodes {
+            let children = self
+                .edges
+                .iter()
+                .filter(|(parent, _)| parent == name)
+                .map(|(_, child)| entities[child])
+                .collect::<Vec<_>>();
+            if !children.is_empty() {
+                world.entity_mut(entities[name]).push_children(&children);
+            }
+        }
+        entities
+    }
+
+    /// Compares the edges of the named entities in `world` against this spec.
+    ///
+    /// Returns one line per difference, empty if the hierarchy matches:
+    /// `- a -> b` for an expected edge missing from the world, `+ a -> b` for an unexpected
+    /// edge in the world, and `! a -> b ...` for an edge only recorded on one side.
+    /// Unnamed entities are shown with their [`Entity`] id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node of the spec is missing from `entities`.
+    pub fn mismatches(&self, world: &World, entities: &BTreeMap<String, Entity>) -> Vec<String> {
+        let names = entities
+            .iter()
+            .map(|(name, entity)| (*entity, name.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let name = |entity: Entity| {
+            names
+                .get(&entity)
+                .cloned()
+                .unwrap_or_else(|| format!("{entity:?}"))
+        };
+
+        let mut children_edges = BTreeSet::new();
+        let mut parents_edges = BTreeSet::new();
+        for &entity in entities.values() {
+            for &child in world.get::<Children>(entity).into_iter().flatten() {
+                children_edges.insert((entity, child));
+            }
+            for &parent in world.get::<Parents>(entity).into_iter().flatten() {
+                parents_edges.insert((parent, entity));
+            }
+        }
+
+        let mut lines = Vec::new();
+        for (parent, child) in &self.edges {
+            let (Some(&parent_entity), Some(&child_entity)) =
+                (entities.get(parent), entities.get(child))
+            else {
+                panic!("no entity given for `{parent}` or `{child}`");
+            };
+            let edge = (parent_entity, child_entity);
+            if !children_edges.contains(&edge) && !parents_edges.contains(&edge) {
+                lines.push(format!("- {parent} -> {child}"));
+            }
+        }
+        for &edge @ (parent, child) in children_edges.union(&parents_edges) {
+            let line = format!("{} -> {}", name(parent), name(child));
+            let expected = self.edges.contains(&(name(parent), name(child)));
+            match (
+                children_edges.contains(&edge),
+                parents_edges.contains(&edge),
+            ) {
+                (true, true) if !expected => lines.push(format!("+ {line}")),
+                (true, false) => lines.push(format!(
+                    "! {line} is in the Children of {} but not in the Parents of {}",
+                    name(parent),
+                    name(child)
+                )),
+                (false, true) => lines.push(format!(
+                    "! {line} is in the Parents of {} but not in the Children of {}",
+                    name(child),
+                    name(parent)
+                )),
+                _ => {}
+            }
+        }
+        lines
+    }
+}
+
+/// Asserts that the [`Parents`] and [`Children`] of named entities match a [`HierarchySpec`].
+///
+/// Takes a [`World`], a map from names to entities as returned by [`HierarchySpec::spawn`],
+/// and a spec string. On failure, panics with the lines of [`HierarchySpec::mismatches`].
+///
+/// [`Parents`]: crate::Parents
+/// [`Children`]: crate::Children
+/// [`World`]: bevy_ecs::world::World
+#[macro_export]
+macro_rules! assert_hierarchy_eq {
+    ($world:expr, $entities:expr, $spec:expr $(,)?) => {{
+        let spec: $crate::HierarchySpec = $spec.parse().unwrap();
+        let mismatches = spec.mismatches(&$world, &$entities);
+        if !mismatches.is_empty() {
+            panic!(
+                "hierarchy does not match `{}`:\n{}",
+                $spec,
+                mismatches.join("\n")
+            );
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::World;
+
+    use super::{HierarchySpec, ParseHierarchySpecError};
+    use crate::{BuildWorldChildren, Children};
+
+    #[test]
+    fn parse() {
+        let spec: HierarchySpec = "a -> b, c -> d\n e -> d; f".parse().unwrap();
+        assert_eq!(spec.nodes(), ["a", "b", "c", "d", "e", "f"]);
+        assert_eq!(
+            spec.edges().collect::<Vec<_>>(),
+            [("a", "b"), ("a", "c"), ("b", "d"), ("c", "d"), ("e", "d")]
+        );
+
+        assert_eq!(
+            "a -> , b".parse::<HierarchySpec>(),
+            Err(ParseHierarchySpecError {
+                statement: "a -> , b".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn spawn_and_compare() {
+        let mut world = World::new();
+        let spec: HierarchySpec = "a -> b, c; c -> d; e -> d".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        assert_eq!(
+            world.get::<Children>(entities["a"]).unwrap().to_vec(),
+            [entities["b"], entities["c"]]
+        );
+        assert_hierarchy_eq!(world, entities, "a -> b, c; c -> d; e -> d");
+
+        world
+            .entity_mut(entities["e"])
+            .move_child(entities["b"], entities["d"]);
+        // Break a back-reference by hand.
+        world
+            .get_mut::<Children>(entities["a"])
+            .unwrap()
+            .remove(&entities["c"]);
+
+        let expected: HierarchySpec = "a -> b, c; c -> d; e -> d".parse().unwrap();
+        assert_eq!(
+            expected.mismatches(&world, &entities),
+            [
+                "- e -> d",
+                "! a -> c is in the Parents of c but not in the Children of a",
+                "+ b -> d",
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "hierarchy does not match `a -> b`:\n- a -> b")]
+    fn assert_hierarchy_eq_panics() {
+        let mut world = World::new();
+        let entities = "a; b".parse::<HierarchySpec>().unwrap().spawn(&mut world);
+        assert_hierarchy_eq!(world, entities, "a -> b");
+    }
+}
@@ -0,0 +1,660 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use bevy_ecs::{
+    component::Tick,
+    entity::Entity,
+    reflect::AppTypeRegistry,
+    system::{Command, Commands, Resource},
+    world::{Mut, World},
+};
+use bevy_reflect::Reflect;
+use bevy_utils::tracing::debug;
+
+use crate::{
+    clone_subtree::{insert_reflected, reflect_components},
+    hierarchy::despawn_entities,
+    BuildWorldChildren, HierarchyEvent,
+};
+
+/// An invertible hierarchy edit, recorded from a [`HierarchyEvent`].
+#[derive(Debug)]
+enum JournalOp {
+    Added {
+        child: Entity,
+        parent: Entity,
+    },
+    Removed {
+        child: Entity,
+        parent: Entity,
+    },
+    Moved {
+        child: Entity,
+        previous_parent: Entity,
+        new_parent: Entity,
+    },
+    Despawned {
+        /// The entity the despawn was requested on.
+        root: Entity,
+        /// The despawned entities, with their reflected components.
+        entities: Vec<(Entity, Vec<Box<dyn Reflect>>)>,
+    },
+}
+
+#[derive(Debug)]
+struct JournalEntry {
+    name: Option<Cow<'static, str>>,
+    /// The change tick the entry was recorded at, for entries outside of a group.
+    tick: Option<Tick>,
+    ops: Vec<JournalOp>,
+}
+
+/// Opt-in resource recording hierarchy edits, so that they can be undone and redone.
+///
+/// Once inserted, every edit sending a [`HierarchyEvent`] is recorded: pushed, removed, moved and
+/// replaced children, [`BuildWorldChildren::clear`] and recursive despawns. Despawned entities
+/// are recorded with their components, cloned through reflection with the [`AppTypeRegistry`]
+/// resource, so that undoing a despawn brings them back. Components that are not registered are
+/// lost.
+///
+/// Edits between [`HierarchyJournal::begin_group`] and [`HierarchyJournal::end_group`] are undone
+/// as one named group. Edits outside of a group are grouped by world change tick, so that
+/// the commands applied at once form a single step. Recording a new edit clears the redo history.
+///
+/// Undone despawns respawn entities with a fresh [`Entity`] id, which the rest of the history then
+/// refers to. The old ids are never brought back, since other code may still hold them.
+#[derive(Resource, Debug, Default)]
+pub struct HierarchyJournal {
+    undo: Vec<JournalEntry>,
+    redo: Vec<JournalEntry>,
+    group: Option<JournalEntry>,
+    despawned: BTreeMap<Entity, Vec<Box<dyn Reflect>>>,
+}
+
+impl HierarchyJournal {
+    /// Starts a named group of edits, ending the current one if any.
+    pub fn begin_group(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.end_group();
+        self.group = Some(JournalEntry {
+            name: Some(name.into()),
+            tick: None,
+            ops: Vec::new(),
+        });
+    }
+
+    /// Ends the current group. Does nothing if no edit was recorded since it began.
+    pub fn end_group(&mut self) {
+        if let Some(entry) = self.group.take() {
+            if !entry.ops.is_empty() {
+                self.undo.push(entry);
+            }
+        }
+    }
+
+    /// Returns `true` if there is a recorded step to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    /// Returns `true` if there is an undone step to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// The name of the step [`undo_hierarchy`] would undo, if it is a named group.
+    pub fn undo_name(&self) -> Option<&str> {
+        self.undo.last()?.name.as_deref()
+    }
+
+    /// The name of the step [`redo_hierarchy`] would redo, if it is a named group.
+    pub fn redo_name(&self) -> Option<&str> {
+        self.redo.last()?.name.as_deref()
+    }
+
+    /// Forgets the whole history, and ends the current group.
+    pub fn clear(&mut self) {
+        *self = HierarchyJournal::default();
+    }
+
+    /// Records `events`, sent at `tick`.
+    pub(crate) fn record(&mut self, events: &[HierarchyEvent], tick: Tick) {
+        let ops = events
+            .iter()
+            .map(|event| match event {
+                &HierarchyEvent::ChildAdded { child, parent } => JournalOp::Added { child, parent },
+                &HierarchyEvent::ChildRemoved { child, parent } => {
+                    JournalOp::Removed { child, parent }
+                }
+                &HierarchyEvent::ChildMoved {
+                    child,
+                    previous_parent,
+                    new_parent,
+                } => JournalOp::Moved {
+                    child,
+                    previous_parent,
+                    new_parent,
+                },
+                HierarchyEvent::SubtreeDespawned { root, entities } => JournalOp::Despawned {
+                    root: *root,
+                    entities: entities
+                        .iter()
+                        .map(|entity| (*entity, self.despawned.remove(entity).unwrap_or_default()))
+                        .collect(),
+                },
+            })
+            .collect::<Vec<_>>();
+        if ops.is_empty() {
+            return;
+        }
+        self.redo.clear();
+
+        if let Some(group) = &mut self.group {
+            group.ops.extend(ops);
+        } else if let Some(entry) = self
+            .undo
+            .last_mut()
+            .filter(|entry| entry.tick == Some(tick))
+        {
+            entry.ops.extend(ops);
+        } else {
+            self.undo.push(JournalEntry {
+                name: None,
+                tick: Some(tick),
+                ops,
+            });
+        }
+    }
+
+    /// Makes the whole history refer to `to` instead of `from`.
+    fn rename(&mut self, from: Entity, to: Entity) {
+        for entry in self.undo.iter_mut().chain(&mut self.redo) {
+            rename_ops(&mut entry.ops, from, to);
+        }
+    }
+}
+
+fn rename_ops(ops: &mut [JournalOp], from: Entity, to: Entity) {
+    let rename = |entity: &mut Entity| {
+        if *entity == from {
+            *entity = to;
+        }
+    };
+    for op in ops {
+        match op {
+            JournalOp::Added { child, parent } | JournalOp::Removed { child, parent } => {
+                rename(child);
+                rename(parent);
+            }
+            JournalOp::Moved {
+                child,
+                previous_parent,
+                new_parent,
+            } => {
+                rename(child);
+                rename(previous_parent);
+                rename(new_parent);
+            }
+            JournalOp::Despawned { root, entities } => {
+                rename(root);
+                for (entity, _) in entities {
+                    rename(entity);
+                }
+            }
+        }
+    }
+}
+
+/// Clones the components of `entities` before they are despawned, if there is a
+/// [`HierarchyJournal`].
+pub(crate) fn journal_despawn(world: &mut World, entities: &[Entity]) {
+    if !world.contains_resource::<HierarchyJournal>() {
+        return;
+    }
+    let components = capture(world, entities);
+    world
+        .resource_mut::<HierarchyJournal>()
+        .despawned
+        .extend(components);
+}
+
+fn capture(world: &World, entities: &[Entity]) -> Vec<(Entity, Vec<Box<dyn Reflect>>)> {
+    let registry = world.get_resource::<AppTypeRegistry>().cloned();
+    let registry = registry.as_ref().map(|registry| registry.read());
+    entities
+        .iter()
+        .map(|&entity| {
+            let components = match (&registry, world.get_entity(entity)) {
+                (Some(registry), Some(_)) => reflect_components(world, registry, entity),
+                _ => Vec::new(),
+            };
+            (entity, components)
+        })
+        .collect()
+}
+
+fn exists(world: &World, entities: &[Entity]) -> bool {
+    entities
+        .iter()
+        .all(|entity| world.get_entity(*entity).is_some())
+}
+
+/// Undoes the last step recorded by the [`HierarchyJournal`], ending the current group first.
+///
+/// Edits of entities that no longer exist are skipped. The edits made send their
+/// [`HierarchyEvent`]s as usual, without being recorded in the journal.
+/// Returns `false` if there is no journal or nothing to undo.
+pub fn undo_hierarchy(world: &mut World) -> bool {
+    if !world.contains_resource::<HierarchyJournal>() {
+        debug!("Failed to undo: no HierarchyJournal resource");
+        return false;
+    }
+    // The journal is out of the world meanwhile, so the undo itself is not recorded.
+    world.resource_scope(|world, mut journal: Mut<HierarchyJournal>| {
+        journal.end_group();
+        let Some(mut entry) = journal.undo.pop() else {
+            return false;
+        };
+        for index in (0..entry.ops.len()).rev() {
+            match &entry.ops[index] {
+                &JournalOp::Added { child, parent } => {
+                    if exists(world, &[child, parent]) {
+                        world.entity_mut(parent).remove_children(&[child]);
+                    }
+                }
+                &JournalOp::Removed { child, parent } => {
+                    if exists(world, &[child, parent]) {
+                        world.entity_mut(parent).push_children(&[child]);
+                    }
+                }
+                &JournalOp::Moved {
+                    child,
+                    previous_parent,
+                    new_parent,
+                } => {
+                    if exists(world, &[child, previous_parent, new_parent]) {
+                        world
+                            .entity_mut(new_parent)
+                            .move_child(previous_parent, child);
+                    }
+                }
+                JournalOp::Despawned { entities, .. } => {
+                    let registry = world.get_resource::<AppTypeRegistry>().cloned();
+                    let mut renamed = Vec::new();
+                    for (entity, components) in entities {
+                        let restored = world.spawn_empty().id();
+                        if let Some(registry) = &registry {
+                            insert_reflected(world, &registry.read(), restored, components);
+                        }
+                        renamed.push((*entity, restored));
+                    }
+                    for (from, to) in renamed {
+                        rename_ops(&mut entry.ops, from, to);
+                        journal.rename(from, to);
+                    }
+                }
+            }
+        }
+        journal.redo.push(entry);
+        true
+    })
+}
+
+/// Redoes the last step undone by [`undo_hierarchy`].
+///
+/// Edits of entities that no longer exist are skipped. The edits made send their
+/// [`HierarchyEvent`]s as usual, without being recorded in the journal.
+/// Returns `false` if there is no journal or nothing to redo.
+pub fn redo_hierarchy(world: &mut World) -> bool {
+    if !world.contains_resource::<HierarchyJournal>() {
+        debug!("Failed to redo: no HierarchyJournal resource");
+        return false;
+    }
+    world.resource_scope(|world, mut journal: Mut<HierarchyJournal>| {
+        journal.end_group();
+        let Some(mut entry) = journal.redo.pop() else {
+            return false;
+        };
+        for op in &mut entry.ops {
+            match op {
+                &mut JournalOp::Added { child, parent } => {
+                    if exists(world, &[child, parent]) {
+                        world.entity_mut(parent).push_children(&[child]);
+                    }
+                }
+                &mut JournalOp::Removed { child, parent } => {
+                    if exists(world, &[child, parent]) {
+                        world.entity_mut(parent).remove_children(&[child]);
+                    }
+                }
+                &mut JournalOp::Moved {
+                    child,
+                    previous_parent,
+                    new_parent,
+                } => {
+                    if exists(world, &[child, previous_parent, new_parent]) {
+                        world
+                            .entity_mut(previous_parent)
+                            .move_child(new_parent, child);
+                    }
+                }
+                JournalOp::Despawned { root, entities } => {
+                    // The edges were already removed by the preceding `Removed` ops, so this
+                    // only sends the `SubtreeDespawned`.
+                    let ids = entities
+                        .iter()
+                        .map(|(entity, _)| *entity)
+                        .filter(|entity| world.get_entity(*entity).is_some())
+                        .collect::<Vec<_>>();
+                    *entities = capture(world, &ids);
+                    despawn_entities(world, *root, ids);
+                }
+            }
+        }
+        journal.undo.push(entry);
+        true
+    })
+}
+
+/// Command that undoes the last hierarchy edit step. See [`undo_hierarchy`].
+#[derive(Debug)]
+pub struct UndoHierarchy;
+
+impl Command for UndoHierarchy {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!("command", name = "UndoHierarchy").entered();
+        undo_hierarchy(world);
+    }
+}
+
+/// Command that redoes the last undone hierarchy edit step. See [`redo_hierarchy`].
+#[derive(Debug)]
+pub struct RedoHierarchy;
+
+impl Command for RedoHierarchy {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!("command", name = "RedoHierarchy").entered();
+        redo_hierarchy(world);
+    }
+}
+
+/// Command that starts a named group of edits. See [`HierarchyJournal::begin_group`].
+#[derive(Debug)]
+pub struct BeginJournalGroup {
+    /// Name of the group.
+    pub name: Cow<'static, str>,
+}
+
+impl Command for BeginJournalGroup {
+    fn apply(self, world: &mut World) {
+        if let Some(mut journal) = world.get_resource_mut::<HierarchyJournal>() {
+            journal.begin_group(self.name);
+        }
+    }
+}
+
+/// Command that ends the current group of edits. See [`HierarchyJournal::end_group`].
+#[derive(Debug)]
+pub struct EndJournalGroup;
+
+impl Command for EndJournalGroup {
+    fn apply(self, world: &mut World) {
+        if let Some(mut journal) = world.get_resource_mut::<HierarchyJournal>() {
+            journal.end_group();
+        }
+    }
+}
+
+/// Trait that holds functions for undoing and redoing hierarchy edits with [`Commands`].
+pub trait HierarchyJournalExt {
+    /// Undoes the last hierarchy edit step. See [`undo_hierarchy`].
+    fn undo_hierarchy(&mut self) -> &mut Self;
+
+    /// Redoes the last undone hierarchy edit step. See [`redo_hierarchy`].
+    fn redo_hierarchy(&mut self) -> &mut Self;
+
+    /// Starts a named group of edits. See [`HierarchyJournal::begin_group`].
+    fn begin_journal_group(&mut self, name: impl Into<Cow<'static, str>>) -> &mut Self;
+
+    /// Ends the current group of edits. See [`HierarchyJournal::end_group`].
+    fn end_journal_group(&mut self) -> &mut Self;
+}
+
+impl<'w, 's> HierarchyJournalExt for Commands<'w, 's> {
+    fn undo_hierarchy(&mut self) -> &mut Self {
+        self.add(UndoHierarchy);
+        self
+    }
+
+    fn redo_hierarchy(&mut self) -> &mut Self {
+        self.add(RedoHierarchy);
+        self
+    }
+
+    fn begin_journal_group(&mut self, name: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.add(BeginJournalGroup { name: name.into() });
+        self
+    }
+
+    fn end_journal_group(&mut self) -> &mut Self {
+        self.add(EndJournalGroup);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        component::Component,
+        event::Events,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+    use bevy_reflect::Reflect;
+
+    use super::{redo_hierarchy, undo_hierarchy, HierarchyJournal, HierarchyJournalExt};
+    use crate::{
+        assert_hierarchy_eq, BuildChildren, BuildWorldChildren, Children, DespawnRecursiveExt,
+        HierarchyEvent::{self, ChildAdded, ChildRemoved, SubtreeDespawned},
+        HierarchySpec, Parents,
+    };
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Value(u32);
+
+    fn journaled_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Value>();
+        world.init_resource::<HierarchyJournal>();
+        world
+    }
+
+    #[test]
+    fn undo_redo_move() {
+        let mut world = journaled_world();
+        let spec: HierarchySpec = "a -> b, c".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        world.increment_change_tick();
+
+        world
+            .entity_mut(entities["a"])
+            .move_child(entities["b"], entities["c"]);
+        assert_hierarchy_eq!(world, entities, "a -> b; b -> c");
+
+        assert!(undo_hierarchy(&mut world));
+        assert_hierarchy_eq!(world, entities, "a -> b, c");
+        assert!(redo_hierarchy(&mut world));
+        assert_hierarchy_eq!(world, entities, "a -> b; b -> c");
+    }
+
+    #[test]
+    fn undo_redo_push_and_remove() {
+        let mut world = journaled_world();
+        let spec: HierarchySpec = "a -> b; c".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+
+        // Edits outside of a group are grouped by change tick.
+        world.increment_change_tick();
+        world
+            .entity_mut(entities["a"])
+            .push_children(&[entities["c"]]);
+        world
+            .entity_mut(entities["b"])
+            .push_children(&[entities["c"]]);
+        world.increment_change_tick();
+        world
+            .entity_mut(entities["a"])
+            .remove_children(&[entities["b"]]);
+        let journal = world.resource::<HierarchyJournal>();
+        assert!(!journal.can_redo());
+        assert_eq!(journal.undo_name(), None);
+
+        assert!(undo_hierarchy(&mut world));
+        assert_hierarchy_eq!(world, entities, "a -> b, c; b -> c");
+        assert!(undo_hierarchy(&mut world));
+        assert_hierarchy_eq!(world, entities, "a -> b; c");
+        assert!(redo_hierarchy(&mut world));
+        assert_hierarchy_eq!(world, entities, "a -> b, c; b -> c");
+    }
+
+    #[test]
+    fn undo_redo_despawn() {
+        let mut world = journaled_world();
+        let spec: HierarchySpec = "a -> b, c; c -> d".parse().unwrap();
+        let mut entities = spec.spawn(&mut world);
+        world.entity_mut(entities["d"]).insert(Value(4));
+        world.increment_change_tick();
+
+        world.entity_mut(entities["c"]).despawn_recursive();
+        assert!(world.get_entity(entities["d"]).is_none());
+
+        // Despawned entities come back with fresh ids and their components.
+        assert!(undo_hierarchy(&mut world));
+        let [c] = <[_; 1]>::try_from(
+            world
+                .get::<Children>(entities["a"])
+                .unwrap()
+                .iter()
+                .filter(|child| **child != entities["b"])
+                .copied()
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let d = world.get::<Children>(c).unwrap().to_vec()[0];
+        assert_ne!(c, entities["c"]);
+        assert_ne!(d, entities["d"]);
+        assert_eq!(world.get::<Value>(d), Some(&Value(4)));
+        entities.insert("c".to_owned(), c);
+        entities.insert("d".to_owned(), d);
+        assert_hierarchy_eq!(world, entities, "a -> b, c; c -> d");
+
+        // The history refers to the new ids.
+        assert!(redo_hierarchy(&mut world));
+        assert!(world.get_entity(c).is_none());
+        assert!(world.get_entity(d).is_none());
+        assert!(world.get::<Parents>(entities["b"]).is_some());
+    }
+
+    #[test]
+    fn undo_redo_events() {
+        let mut world = journaled_world();
+        world.init_resource::<Events<HierarchyEvent>>();
+        let spec: HierarchySpec = "a -> b; b -> c".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        let a = entities["a"];
+        world.resource_mut::<HierarchyJournal>().clear();
+
+        world.entity_mut(entities["b"]).despawn_recursive();
+        let drain = |world: &mut World| {
+            world
+                .resource_mut::<Events<HierarchyEvent>>()
+                .drain()
+                .collect::<Vec<_>>()
+        };
+        drain(&mut world);
+
+        assert!(undo_hierarchy(&mut world));
+        let b = world.get::<Children>(a).unwrap().to_vec()[0];
+        let c = world.get::<Children>(b).unwrap().to_vec()[0];
+        assert_eq!(
+            drain(&mut world),
+            [
+                ChildAdded {
+                    child: c,
+                    parent: b
+                },
+                ChildAdded {
+                    child: b,
+                    parent: a
+                },
+            ]
+        );
+
+        assert!(redo_hierarchy(&mut world));
+        assert_eq!(
+            drain(&mut world),
+            [
+                ChildRemoved {
+                    child: b,
+                    parent: a
+                },
+                ChildRemoved {
+                    child: c,
+                    parent: b
+                },
+                SubtreeDespawned {
+                    root: b,
+                    entities: vec![b, c]
+                },
+            ]
+        );
+
+        // Undoing and redoing is not recorded as a new step.
+        assert!(undo_hierarchy(&mut world));
+        assert!(!undo_hierarchy(&mut world));
+    }
+
+    #[test]
+    fn named_groups() {
+        let mut world = journaled_world();
+        let spec: HierarchySpec = "a -> b, c; c -> d".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.begin_journal_group("clear");
+        commands.entity(entities["c"]).clear_children();
+        commands
+            .entity(entities["a"])
+            .remove_children(&[entities["b"]]);
+        commands.end_journal_group();
+        queue.apply(&mut world);
+        assert_hierarchy_eq!(world, entities, "a -> c; b; d");
+        assert_eq!(
+            world.resource::<HierarchyJournal>().undo_name(),
+            Some("clear")
+        );
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.undo_hierarchy();
+        queue.apply(&mut world);
+        assert_hierarchy_eq!(world, entities, "a -> b, c; c -> d");
+        assert_eq!(
+            world.resource::<HierarchyJournal>().redo_name(),
+            Some("clear")
+        );
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.redo_hierarchy();
+        queue.apply(&mut world);
+        assert_hierarchy_eq!(world, entities, "a -> c; b; d");
+    }
+}
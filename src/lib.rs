@@ -11,25 +11,80 @@ pub use components::*;
 mod hierarchy;
 pub use hierarchy::*;
 
+mod recursive;
+pub use recursive::*;
+
 mod child_builder;
 pub use child_builder::*;
 
+mod fallible;
+pub use fallible::*;
+
 mod events;
 pub use events::*;
 
+mod change_detection;
+pub use change_detection::*;
+
 // mod valid_parent_check_plugin;
 // pub use valid_parent_check_plugin::*;
 
 mod query_extension;
 pub use query_extension::*;
 
+mod subtree_events;
+pub use subtree_events::*;
+
+mod propagation;
+pub use propagation::*;
+
+mod clone_subtree;
+pub use clone_subtree::*;
+
+mod graph_export;
+pub use graph_export::*;
+
+mod hierarchy_spec;
+pub use hierarchy_spec::*;
+
+mod hierarchy_diff;
+pub use hierarchy_diff::*;
+
+mod journal;
+pub use journal::*;
+
+mod replay;
+pub use replay::*;
+
+mod merge;
+pub use merge::*;
+
+mod transaction;
+pub use transaction::*;
+
+mod world_transfer;
+pub use world_transfer::*;
+
+#[cfg(feature = "petgraph")]
+mod petgraph_interop;
+#[cfg(feature = "petgraph")]
+pub use petgraph_interop::*;
+
+#[cfg(feature = "serde")]
+mod snapshot;
+#[cfg(feature = "serde")]
+pub use snapshot::*;
+
 #[doc(hidden)]
 pub mod prelude {
     #[doc(hidden)]
-    pub use crate::{child_builder::*, components::*, query_extension::*};
+    pub use crate::{
+        change_detection::*, child_builder::*, components::*, fallible::*, propagation::*,
+        query_extension::*, recursive::*, subtree_events::*,
+    };
     // pub use crate::{child_builder::*, components::*, hierarchy::*, query_extension::*};
     #[cfg(feature = "bevy_app")]
-    pub use crate::{HierarchyPlugin, ValidParentCheckPlugin};
+    pub use crate::HierarchyPlugin;
 }
 #[cfg(feature = "bevy_app")]
 use bevy_app::prelude::*;
@@ -40,10 +95,10 @@ pub struct HierarchyPlugin;
 #[cfg(feature = "bevy_app")]
 impl Plugin for HierarchyPlugin {
     fn build(&self, app: &mut App) {
-        app
-            // .register_type::<Children>()
-            // .register_type::<Node>()
-            // .register_type::<smallvec::Vec<[bevy_ecs::entity::Entity; 8]>>()
-            .add_event::<HierarchyEvent>();
+        app.register_type::<Children>()
+            .register_type::<Parents>()
+            .add_event::<HierarchyEvent>()
+            .add_event::<HierarchyTransactionFailed>()
+            .add_systems(PostUpdate, propagate_descendants_changed);
     }
 }
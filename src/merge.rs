@@ -0,0 +1,286 @@
+use bevy_ecs::{entity::Entity, system::Command, world::World};
+use bevy_utils::tracing::warn;
+
+use crate::{
+    child_builder::{
+        insert_children_unidirectional, insert_parent_unidirectional, push_events, reachable,
+        remove_children_unidirectional, remove_parent_unidirectional,
+    },
+    fallible::check_entities,
+    journal::journal_despawn,
+    Children, HierarchyError, HierarchyEvent, Parents,
+};
+
+/// What [`merge_nodes`] does with edges that would make the merged node its own parent or close
+/// a cycle through it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeConflicts {
+    /// Drop the conflicting edges and merge the rest.
+    #[default]
+    Skip,
+    /// Keep the edges closing a cycle, only dropping the ones that would make a self-loop.
+    AllowCycles,
+    /// Leave both nodes untouched and return [`HierarchyError::Cycle`].
+    Abort,
+}
+
+/// Command that merges `source` into `target`, then despawns `source`.
+///
+/// See [`merge_nodes`] for details. Failures are logged and skipped.
+#[derive(Debug)]
+pub struct MergeNodes {
+    /// The node that remains.
+    pub target: Entity,
+    /// The node merged into `target` and despawned.
+    pub source: Entity,
+    /// What to do with conflicting edges.
+    pub conflicts: MergeConflicts,
+}
+
+impl Command for MergeNodes {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "command",
+            name = "MergeNodes",
+            entity = bevy_utils::tracing::field::debug(self.target)
+        )
+        .entered();
+        if let Err(error) = merge_nodes(world, self.target, self.source, self.conflicts) {
+            warn!(
+                "Failed to merge {:?} into {:?}: {error}",
+                self.source, self.target
+            );
+        }
+    }
+}
+
+/// Function for contracting two nodes of the hierarchy into one.
+///
+/// Every child of `source` becomes a child of `target`, and every parent of `source` becomes a
+/// parent of `target`, with both sides of each edge updated. Edges `target` already has are kept
+/// once. `source` is then despawned, without its descendants.
+///
+/// An edge conflicts if it would make `target` its own parent, or if it closes a cycle through
+/// `target`: a child of `source` that is an ancestor of either node, or a parent of `source` that
+/// is a descendant of either node. Conflicts are found on the hierarchy before the merge, and
+/// handled according to `conflicts`.
+///
+/// Sends a [`HierarchyEvent::ChildMoved`] from `source` to `target` for every child that was not
+/// a child of `target` yet, a [`HierarchyEvent::ChildRemoved`] for other edges of `source`, a
+/// [`HierarchyEvent::ChildAdded`] for every new parent of `target`, and finally a
+/// [`HierarchyEvent::SubtreeDespawned`] for `source`.
+///
+/// Does nothing if `target` and `source` are the same entity.
+pub fn merge_nodes(
+    world: &mut World,
+    target: Entity,
+    source: Entity,
+    conflicts: MergeConflicts,
+) -> Result<(), HierarchyError> {
+    check_entities(world, &[target, source])?;
+    if target == source {
+        return Ok(());
+    }
+    let nodes = [target, source];
+    let children = world
+        .get::<Children>(source)
+        .map(|c| c.to_vec())
+        .unwrap_or_default();
+    let parents = world
+        .get::<Parents>(source)
+        .map(|p| p.to_vec())
+        .unwrap_or_default();
+
+    let ancestors = reachable::<Parents>(world, &nodes);
+    let descendants = reachable::<Children>(world, &nodes);
+    let keep = |parent: Entity, child: Entity, cycle: bool| {
+        let self_loop = nodes.contains(&parent) && nodes.contains(&child);
+        match conflicts {
+            MergeConflicts::AllowCycles => !self_loop,
+            _ => !self_loop && !cycle,
+        }
+    };
+    let keep_children = children
+        .iter()
+        .map(|&child| keep(source, child, ancestors.contains(&child)))
+        .collect::<Vec<_>>();
+    let keep_parents = parents
+        .iter()
+        .map(|&parent| keep(parent, source, descendants.contains(&parent)))
+        .collect::<Vec<_>>();
+    if conflicts == MergeConflicts::Abort {
+        let conflict = children
+            .iter()
+            .zip(&keep_children)
+            .map(|(&child, keep)| (target, child, keep))
+            .chain(
+                parents
+                    .iter()
+                    .zip(&keep_parents)
+                    .map(|(&parent, keep)| (parent, target, keep)),
+            )
+            .find(|(_, _, keep)| !**keep);
+        if let Some((parent, child, _)) = conflict {
+            let child = if child == source { target } else { child };
+            let parent = if parent == source { target } else { parent };
+            return Err(HierarchyError::Cycle { parent, child });
+        }
+    }
+
+    let mut events = Vec::new();
+    for (child, keep) in children.into_iter().zip(keep_children) {
+        remove_children_unidirectional(world, &[child], source);
+        remove_parent_unidirectional(world, child, source);
+        let already_child = world
+            .get::<Children>(target)
+            .is_some_and(|c| c.contains(&child));
+        if keep && !already_child {
+            insert_children_unidirectional(world, &[child], target);
+            insert_parent_unidirectional(world, child, target);
+            events.push(HierarchyEvent::ChildMoved {
+                child,
+                previous_parent: source,
+                new_parent: target,
+            });
+        } else {
+            events.push(HierarchyEvent::ChildRemoved {
+                child,
+                parent: source,
+            });
+        }
+    }
+    for (parent, keep) in parents.into_iter().zip(keep_parents) {
+        remove_children_unidirectional(world, &[source], parent);
+        remove_parent_unidirectional(world, source, parent);
+        events.push(HierarchyEvent::ChildRemoved {
+            child: source,
+            parent,
+        });
+        let already_parent = world
+            .get::<Parents>(target)
+            .is_some_and(|p| p.contains(&parent));
+        if keep && !already_parent {
+            insert_children_unidirectional(world, &[target], parent);
+            insert_parent_unidirectional(world, target, parent);
+            events.push(HierarchyEvent::ChildAdded {
+                child: target,
+                parent,
+            });
+        }
+    }
+    push_events(world, events);
+
+    journal_despawn(world, &[source]);
+    world.despawn(source);
+    push_events(
+        world,
+        [HierarchyEvent::SubtreeDespawned {
+            root: source,
+            entities: vec![source],
+        }],
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        event::Events,
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+
+    use super::{merge_nodes, MergeConflicts, MergeNodes};
+    use crate::{
+        assert_hierarchy_eq, BuildWorldChildren, HierarchyError, HierarchyEvent, HierarchySpec,
+    };
+
+    #[test]
+    fn merge() {
+        let mut world = World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+        let spec: HierarchySpec = "p -> a, b; q -> b; a -> c; b -> c, d".parse().unwrap();
+        let mut entities = spec.spawn(&mut world);
+        world.resource_mut::<Events<HierarchyEvent>>().clear();
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world).add(MergeNodes {
+            target: entities["a"],
+            source: entities["b"],
+            conflicts: MergeConflicts::default(),
+        });
+        queue.apply(&mut world);
+
+        let b = entities.remove("b").unwrap();
+        assert!(world.get_entity(b).is_none());
+        assert_hierarchy_eq!(world, entities, "p, q -> a; a -> c, d");
+        let events = world
+            .resource_mut::<Events<HierarchyEvent>>()
+            .drain()
+            .collect::<Vec<_>>();
+        let [a, c, d, p, q] = ["a", "c", "d", "p", "q"].map(|name| entities[name]);
+        assert_eq!(
+            events,
+            [
+                HierarchyEvent::ChildRemoved {
+                    child: c,
+                    parent: b
+                },
+                HierarchyEvent::ChildMoved {
+                    child: d,
+                    previous_parent: b,
+                    new_parent: a
+                },
+                HierarchyEvent::ChildRemoved {
+                    child: b,
+                    parent: p
+                },
+                HierarchyEvent::ChildRemoved {
+                    child: b,
+                    parent: q
+                },
+                HierarchyEvent::ChildAdded {
+                    child: a,
+                    parent: q
+                },
+                HierarchyEvent::SubtreeDespawned {
+                    root: b,
+                    entities: vec![b]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_conflicts() {
+        let spec: HierarchySpec = "a -> b; b -> c; c -> x".parse().unwrap();
+
+        // Merging `x` into `a` would make `a -> b -> c -> a`.
+        let mut world = World::new();
+        let entities = spec.spawn(&mut world);
+        let [a, c, x] = ["a", "c", "x"].map(|name| entities[name]);
+        assert_eq!(
+            merge_nodes(&mut world, a, x, MergeConflicts::Abort),
+            Err(HierarchyError::Cycle {
+                parent: c,
+                child: a
+            })
+        );
+        assert_hierarchy_eq!(world, entities, "a -> b; b -> c; c -> x");
+
+        merge_nodes(&mut world, a, x, MergeConflicts::AllowCycles).unwrap();
+        let mut merged = entities.clone();
+        merged.remove("x");
+        assert_hierarchy_eq!(world, merged, "a -> b; b -> c; c -> a");
+
+        let mut world = World::new();
+        let entities = spec.spawn(&mut world);
+        world
+            .entity_mut(entities["b"])
+            .merge_from(entities["c"], MergeConflicts::Skip);
+        let mut merged = entities.clone();
+        merged.remove("c");
+        assert_hierarchy_eq!(world, merged, "a -> b; b -> x");
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy_ecs::{entity::Entity, world::World};
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::{hierarchy::collect_hierarchy, BuildWorldChildren, Children};
+
+/// Converts the hierarchy into a [`DiGraph`] with an edge from each parent to each child.
+///
+/// When `roots` is given, only these entities and their descendants are converted. Otherwise,
+/// every entity with a [`Parents`] or [`Children`] component is. Nodes are added in [`Entity`]
+/// order.
+///
+/// Returns the graph along with the [`NodeIndex`] of each entity.
+///
+/// [`Parents`]: crate::Parents
+pub fn hierarchy_to_digraph(
+    world: &World,
+    roots: Option<&[Entity]>,
+) -> (DiGraph<Entity, ()>, BTreeMap<Entity, NodeIndex>) {
+    let entities = collect_hierarchy(world, roots);
+    let mut graph = DiGraph::with_capacity(entities.len(), 0);
+    let indices = entities
+        .iter()
+        .map(|&entity| (entity, graph.add_node(entity)))
+        .collect::<BTreeMap<_, _>>();
+
+    for (&parent, &parent_index) in &indices {
+        for child in world.get::<Children>(parent).into_iter().flatten() {
+            if let Some(&child_index) = indices.get(child) {
+                graph.add_edge(parent_index, child_index, ());
+            }
+        }
+    }
+    (graph, indices)
+}
+
+/// Makes the edges between the entities of `graph` match its edges.
+///
+/// Edges between an entity of `graph` and an entity outside of it are left untouched, as are
+/// despawned entities. Missing edges are added with [`BuildWorldChildren::push_children`] and
+/// extra ones removed with [`BuildWorldChildren::remove_children`], which send the matching
+/// [`HierarchyEvent`]s. All removals happen before any addition.
+///
+/// [`HierarchyEvent`]: crate::HierarchyEvent
+pub fn apply_digraph(world: &mut World, graph: &DiGraph<Entity, ()>) {
+    let entities = graph
+        .node_weights()
+        .copied()
+        .filter(|entity| world.get_entity(*entity).is_some())
+        .collect::<BTreeSet<_>>();
+
+    let mut desired = BTreeMap::<Entity, BTreeSet<Entity>>::new();
+    for edge in graph.raw_edges() {
+        let (parent, child) = (graph[edge.source()], graph[edge.target()]);
+        if entities.contains(&parent) && entities.contains(&child) {
+            desired.entry(parent).or_default().insert(child);
+        }
+    }
+
+    let mut additions = Vec::new();
+    for &parent in &entities {
+        let current = world
+            .get::<Children>(parent)
+            .into_iter()
+            .flatten()
+            .filter(|child| entities.contains(child))
+            .copied()
+            .collect::<BTreeSet<_>>();
+        let desired = desired.remove(&parent).unwrap_or_default();
+
+        let removed = current.difference(&desired).copied().collect::<Vec<_>>();
+        if !removed.is_empty() {
+            world.entity_mut(parent).remove_children(&removed);
+        }
+        let added = desired.difference(&current).copied().collect::<Vec<_>>();
+        if !added.is_empty() {
+            additions.push((parent, added));
+        }
+    }
+    for (parent, added) in additions {
+        world.entity_mut(parent).push_children(&added);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{event::Events, world::World};
+    use petgraph::algo::is_cyclic_directed;
+
+    use super::{apply_digraph, hierarchy_to_digraph};
+    use crate::{assert_hierarchy_eq, BuildWorldChildren, HierarchyEvent, HierarchySpec};
+
+    #[test]
+    fn digraph_round_trip() {
+        let mut world = World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+        let spec: HierarchySpec = "a -> b, c; c -> d; x -> d".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        world.resource_mut::<Events<HierarchyEvent>>().clear();
+
+        let (mut graph, indices) = hierarchy_to_digraph(&world, Some(&[entities["a"]]));
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+        assert!(!is_cyclic_directed(&graph));
+
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| indices[&entities[name]]);
+        let edge = graph.find_edge(c, d).unwrap();
+        graph.remove_edge(edge);
+        graph.add_edge(b, d, ());
+        graph.add_edge(a, d, ());
+        apply_digraph(&mut world, &graph);
+
+        // The edge from `x`, outside of the graph, is kept.
+        assert_hierarchy_eq!(world, entities, "a -> b, c, d; b -> d; x -> d");
+        let events = world
+            .resource_mut::<Events<HierarchyEvent>>()
+            .drain()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                HierarchyEvent::ChildRemoved {
+                    child: entities["d"],
+                    parent: entities["c"],
+                },
+                HierarchyEvent::ChildAdded {
+                    child: entities["d"],
+                    parent: entities["a"],
+                },
+                HierarchyEvent::ChildAdded {
+                    child: entities["d"],
+                    parent: entities["b"],
+                },
+            ]
+        );
+
+        world
+            .entity_mut(entities["d"])
+            .push_children(&[entities["a"]]);
+        let (graph, _) = hierarchy_to_digraph(&world, None);
+        assert_eq!(graph.node_count(), 5);
+        assert!(is_cyclic_directed(&graph));
+    }
+}
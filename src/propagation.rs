@@ -0,0 +1,235 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    system::{Command, EntityCommands},
+    world::{EntityMut, World},
+};
+
+use crate::{Children, Parents};
+
+/// The direction in which a dispatched event travels through the hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagation {
+    /// Delivered to the target, then up through its [`Parents`] to every ancestor.
+    Bubble,
+    /// Delivered to the target, then down through its [`Children`] to every descendant.
+    Capture,
+}
+
+/// Returned by a [`Listener`] to decide whether the event keeps travelling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Propagate {
+    /// Deliver the event to the next entities.
+    Continue,
+    /// Do not deliver the event past this entity. Entities reached along other paths still
+    /// receive it.
+    Stop,
+}
+
+/// Handles events of type `E` dispatched to or propagated through this entity.
+///
+/// See [`DispatchEventExt`] for dispatching events.
+#[derive(Component)]
+pub struct Listener<E: 'static> {
+    handler: Option<Box<dyn FnMut(&mut World, Entity, &mut E) -> Propagate + Send + Sync>>,
+}
+
+impl<E: 'static> Listener<E> {
+    /// Constructs a [`Listener`] calling `handler` with the world, the listening entity and the event.
+    ///
+    /// The handler is taken out of the listener while it runs, so an event dispatched from
+    /// within `handler` skips this entity.
+    pub fn new(
+        handler: impl FnMut(&mut World, Entity, &mut E) -> Propagate + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Some(Box::new(handler)),
+        }
+    }
+}
+
+/// Command that dispatches an event to an entity and propagates it through the hierarchy.
+#[derive(Debug)]
+pub struct DispatchEvent<E> {
+    /// Entity the event is dispatched to.
+    pub target: Entity,
+    /// The dispatched event.
+    pub event: E,
+    /// The direction the event travels in.
+    pub propagation: Propagation,
+}
+
+impl<E: Send + 'static> Command for DispatchEvent<E> {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "command",
+            name = "DispatchEvent",
+            entity = bevy_utils::tracing::field::debug(self.target)
+        )
+        .entered();
+        dispatch_event(world, self.target, self.event, self.propagation);
+    }
+}
+
+/// Function for dispatching an event to `target` and propagating it through the hierarchy.
+///
+/// Entities are visited breadth-first, so nearer entities receive the event first. In a graph
+/// where an entity is reachable along several paths, it still receives the event exactly once.
+/// Entities without a [`Listener<E>`] are passed through. A listener returning
+/// [`Propagate::Stop`] only prunes the paths that go through its entity.
+///
+/// Returns the event, as modified by the listeners.
+pub fn dispatch_event<E: 'static>(
+    world: &mut World,
+    target: Entity,
+    mut event: E,
+    propagation: Propagation,
+) -> E {
+    let mut visited = BTreeSet::from([target]);
+    let mut nexts = VecDeque::from([target]);
+
+    while let Some(entity) = nexts.pop_front() {
+        let handler = world
+            .get_mut::<Listener<E>>(entity)
+            .and_then(|mut listener| listener.handler.take());
+        if let Some(mut handler) = handler {
+            let propagate = handler(world, entity, &mut event);
+            // The handler may have despawned the entity or replaced its listener.
+            if let Some(mut listener) = world.get_mut::<Listener<E>>(entity) {
+                listener.handler.get_or_insert(handler);
+            }
+            if propagate == Propagate::Stop {
+                continue;
+            }
+        }
+
+        let neighbors = match propagation {
+            Propagation::Bubble => world.get::<Parents>(entity).map(|p| p.to_vec()),
+            Propagation::Capture => world.get::<Children>(entity).map(|c| c.to_vec()),
+        };
+        for neighbor in neighbors.into_iter().flatten() {
+            if visited.insert(neighbor) {
+                nexts.push_back(neighbor);
+            }
+        }
+    }
+
+    event
+}
+
+/// Trait that holds functions for dispatching events along the hierarchy.
+pub trait DispatchEventExt {
+    /// Dispatches `event` to this entity and propagates it in the direction of `propagation`.
+    ///
+    /// See [`dispatch_event`] for the order in which entities receive it.
+    fn dispatch_event<E: Send + 'static>(
+        &mut self,
+        event: E,
+        propagation: Propagation,
+    ) -> &mut Self;
+}
+
+impl<'w, 's, 'a> DispatchEventExt for EntityCommands<'w, 's, 'a> {
+    fn dispatch_event<E: Send + 'static>(
+        &mut self,
+        event: E,
+        propagation: Propagation,
+    ) -> &mut Self {
+        let target = self.id();
+        self.commands().add(DispatchEvent {
+            target,
+            event,
+            propagation,
+        });
+        self
+    }
+}
+
+impl<'w> DispatchEventExt for EntityMut<'w> {
+    fn dispatch_event<E: Send + 'static>(
+        &mut self,
+        event: E,
+        propagation: Propagation,
+    ) -> &mut Self {
+        let target = self.id();
+
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "dispatch_event",
+            entity = bevy_utils::tracing::field::debug(target)
+        )
+        .entered();
+
+        self.world_scope(|world| {
+            dispatch_event(world, target, event, propagation);
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{entity::Entity, world::World};
+
+    use super::{dispatch_event, Listener, Propagate, Propagation};
+    use crate::BuildWorldChildren;
+
+    /// Records the entities it was delivered to.
+    struct Signal(Vec<Entity>);
+
+    fn record(_: &mut World, entity: Entity, signal: &mut Signal) -> Propagate {
+        signal.0.push(entity);
+        Propagate::Continue
+    }
+
+    #[test]
+    fn bubble_visits_shared_ancestors_once() {
+        let world = &mut World::new();
+
+        let [a, b, c, d] =
+            std::array::from_fn(|_| world.spawn(Listener::<Signal>::new(record)).id());
+
+        // a -> b, c; b -> d; c -> d
+        world.entity_mut(a).push_children(&[b, c]);
+        world.entity_mut(b).push_children(&[d]);
+        world.entity_mut(c).push_children(&[d]);
+
+        let signal = dispatch_event(world, d, Signal(Vec::new()), Propagation::Bubble);
+        assert_eq!(signal.0, [d, b, c, a]);
+
+        let signal = dispatch_event(world, a, Signal(Vec::new()), Propagation::Capture);
+        assert_eq!(signal.0, [a, b, c, d]);
+    }
+
+    #[test]
+    fn stop_propagation() {
+        let world = &mut World::new();
+
+        let [a, c, d] = std::array::from_fn(|_| world.spawn(Listener::<Signal>::new(record)).id());
+        let b = world
+            .spawn(Listener::new(
+                |_: &mut World, entity, signal: &mut Signal| {
+                    signal.0.push(entity);
+                    Propagate::Stop
+                },
+            ))
+            .id();
+
+        // a -> b, d; b -> c
+        world.entity_mut(a).push_children(&[b, d]);
+        world.entity_mut(b).push_children(&[c]);
+
+        let signal = dispatch_event(world, c, Signal(Vec::new()), Propagation::Bubble);
+        assert_eq!(signal.0, [c, b]);
+
+        // Stopping at `b` leaves its sibling branch untouched.
+        let signal = dispatch_event(world, a, Signal(Vec::new()), Propagation::Capture);
+        assert_eq!(signal.0, [a, d, b]);
+
+        // The listener is put back after handling.
+        assert!(world.get::<Listener<Signal>>(b).is_some());
+    }
+}
@@ -0,0 +1,279 @@
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::hierarchy::collect_subtree_until;
+use bevy_ecs::{
+    bundle::Bundle,
+    component::Component,
+    entity::Entity,
+    system::{Command, EntityCommands},
+    world::{EntityMut, World},
+};
+use bevy_utils::tracing::debug;
+
+/// Inserts a bundle on the given entity and all its descendants
+#[derive(Debug)]
+pub struct InsertRecursive<B> {
+    /// Target entity
+    pub entity: Entity,
+    /// Bundle cloned onto every visited entity
+    pub bundle: B,
+    /// Descendants with a component of this type are skipped, together with their descendants
+    pub stop_at: Option<TypeId>,
+}
+
+/// Removes a bundle from the given entity and all its descendants
+#[derive(Debug)]
+pub struct RemoveRecursive<B> {
+    /// Target entity
+    pub entity: Entity,
+    /// Descendants with a component of this type are skipped, together with their descendants
+    pub stop_at: Option<TypeId>,
+    /// Marker for the removed bundle
+    pub phantom: PhantomData<B>,
+}
+
+/// Returns `entity` and its descendants, leaving out the subtrees of descendants with a component
+/// of type `stop_at`. Every entity is returned once, even if it has several parents.
+///
+/// Children that no longer exist, as left behind by a plain despawn, are skipped.
+fn collect_recursive(world: &World, entity: Entity, stop_at: Option<TypeId>) -> Vec<Entity> {
+    collect_subtree_until(world, entity, |e| match world.get_entity(e) {
+        Some(e) => stop_at.is_some_and(|type_id| e.contains_type_id(type_id)),
+        None => true,
+    })
+}
+
+/// Function for inserting `bundle` on an entity and its descendants
+///
+/// Descendants with a component of type `stop_at` are skipped, along with the entities only
+/// reachable through them. The entity itself is always visited.
+pub fn insert_recursive<B: Bundle + Clone>(
+    world: &mut World,
+    entity: Entity,
+    bundle: B,
+    stop_at: Option<TypeId>,
+) {
+    if world.get_entity(entity).is_none() {
+        debug!("Failed to insert recursively on entity {:?}", entity);
+        return;
+    }
+    for e in collect_recursive(world, entity, stop_at) {
+        world.entity_mut(e).insert(bundle.clone());
+    }
+}
+
+/// Function for removing the bundle `B` from an entity and its descendants
+///
+/// Descendants with a component of type `stop_at` are skipped, along with the entities only
+/// reachable through them. The entity itself is always visited.
+pub fn remove_recursive<B: Bundle>(world: &mut World, entity: Entity, stop_at: Option<TypeId>) {
+    if world.get_entity(entity).is_none() {
+        debug!("Failed to remove recursively from entity {:?}", entity);
+        return;
+    }
+    for e in collect_recursive(world, entity, stop_at) {
+        world.entity_mut(e).remove::<B>();
+    }
+}
+
+impl<B: Bundle + Clone> Command for InsertRecursive<B> {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "command",
+            name = "InsertRecursive",
+            entity = bevy_utils::tracing::field::debug(self.entity)
+        )
+        .entered();
+        insert_recursive(world, self.entity, self.bundle, self.stop_at);
+    }
+}
+
+impl<B: Bundle> Command for RemoveRecursive<B> {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "command",
+            name = "RemoveRecursive",
+            entity = bevy_utils::tracing::field::debug(self.entity)
+        )
+        .entered();
+        remove_recursive::<B>(world, self.entity, self.stop_at);
+    }
+}
+
+/// Trait that holds functions for inserting and removing components recursively down the hierarchy
+///
+/// Each entity is visited once, even if it is reachable through several parents.
+pub trait RecursiveComponentsExt {
+    /// Inserts `bundle` on this entity and all its descendants.
+    fn insert_recursive<B: Bundle + Clone>(&mut self, bundle: B) -> &mut Self;
+
+    /// Inserts `bundle` on this entity and its descendants, skipping descendants with an `S`
+    /// component and their own descendants.
+    fn insert_recursive_until<S: Component, B: Bundle + Clone>(&mut self, bundle: B) -> &mut Self;
+
+    /// Removes the bundle `B` from this entity and all its descendants.
+    fn remove_recursive<B: Bundle>(&mut self) -> &mut Self;
+
+    /// Removes the bundle `B` from this entity and its descendants, skipping descendants with an
+    /// `S` component and their own descendants.
+    fn remove_recursive_until<S: Component, B: Bundle>(&mut self) -> &mut Self;
+}
+
+impl<'w, 's, 'a> RecursiveComponentsExt for EntityCommands<'w, 's, 'a> {
+    fn insert_recursive<B: Bundle + Clone>(&mut self, bundle: B) -> &mut Self {
+        let entity = self.id();
+        self.commands().add(InsertRecursive {
+            entity,
+            bundle,
+            stop_at: None,
+        });
+        self
+    }
+
+    fn insert_recursive_until<S: Component, B: Bundle + Clone>(&mut self, bundle: B) -> &mut Self {
+        let entity = self.id();
+        self.commands().add(InsertRecursive {
+            entity,
+            bundle,
+            stop_at: Some(TypeId::of::<S>()),
+        });
+        self
+    }
+
+    fn remove_recursive<B: Bundle>(&mut self) -> &mut Self {
+        let entity = self.id();
+        self.commands().add(RemoveRecursive::<B> {
+            entity,
+            stop_at: None,
+            phantom: PhantomData,
+        });
+        self
+    }
+
+    fn remove_recursive_until<S: Component, B: Bundle>(&mut self) -> &mut Self {
+        let entity = self.id();
+        self.commands().add(RemoveRecursive::<B> {
+            entity,
+            stop_at: Some(TypeId::of::<S>()),
+            phantom: PhantomData,
+        });
+        self
+    }
+}
+
+impl<'w> RecursiveComponentsExt for EntityMut<'w> {
+    fn insert_recursive<B: Bundle + Clone>(&mut self, bundle: B) -> &mut Self {
+        let entity = self.id();
+        self.world_scope(|world| insert_recursive(world, entity, bundle, None));
+        self
+    }
+
+    fn insert_recursive_until<S: Component, B: Bundle + Clone>(&mut self, bundle: B) -> &mut Self {
+        let entity = self.id();
+        self.world_scope(|world| {
+            insert_recursive(world, entity, bundle, Some(TypeId::of::<S>()));
+        });
+        self
+    }
+
+    fn remove_recursive<B: Bundle>(&mut self) -> &mut Self {
+        let entity = self.id();
+        self.world_scope(|world| remove_recursive::<B>(world, entity, None));
+        self
+    }
+
+    fn remove_recursive_until<S: Component, B: Bundle>(&mut self) -> &mut Self {
+        let entity = self.id();
+        self.world_scope(|world| {
+            remove_recursive::<B>(world, entity, Some(TypeId::of::<S>()));
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bevy_ecs::{
+        component::Component,
+        entity::Entity,
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+
+    use super::RecursiveComponentsExt;
+    use crate::HierarchySpec;
+
+    #[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+    struct Tagged(u32);
+
+    #[derive(Component)]
+    struct Boundary;
+
+    /// Names of the entities with a [`Tagged`], in alphabetical order.
+    fn counted(world: &World, entities: &BTreeMap<String, Entity>) -> Vec<String> {
+        entities
+            .iter()
+            .filter(|(_, entity)| world.get::<Tagged>(**entity).is_some())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn insert_and_remove_recursive() {
+        let mut world = World::new();
+        // `d` is shared by two parents, `e` is under the boundary `c`.
+        let spec: HierarchySpec = "a -> b, c; b, c -> d; c -> e".parse().unwrap();
+        let entities = spec.spawn(&mut world);
+        world.entity_mut(entities["c"]).insert(Boundary);
+
+        world
+            .entity_mut(entities["a"])
+            .insert_recursive_until::<Boundary, _>(Tagged(0));
+        assert_eq!(counted(&world, &entities), ["a", "b", "d"]);
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world)
+            .entity(entities["a"])
+            .insert_recursive(Tagged(1));
+        queue.apply(&mut world);
+        assert_eq!(counted(&world, &entities), ["a", "b", "c", "d", "e"]);
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world)
+            .entity(entities["a"])
+            .remove_recursive_until::<Boundary, Tagged>();
+        queue.apply(&mut world);
+        assert_eq!(counted(&world, &entities), ["c", "e"]);
+
+        // The root is visited even if it has the marker.
+        world
+            .entity_mut(entities["c"])
+            .remove_recursive_until::<Boundary, Tagged>();
+        assert!(counted(&world, &entities).is_empty());
+    }
+
+    #[test]
+    fn dangling_child() {
+        let mut world = World::new();
+        let entities = "a -> b, c"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        // A plain despawn leaves `b` in the children of `a`.
+        world.despawn(entities["b"]);
+
+        world.entity_mut(entities["a"]).insert_recursive(Tagged(0));
+        assert_eq!(counted(&world, &entities), ["a", "c"]);
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world)
+            .entity(entities["a"])
+            .remove_recursive::<Tagged>();
+        queue.apply(&mut world);
+        assert!(counted(&world, &entities).is_empty());
+    }
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use bevy_ecs::{
+    entity::Entity,
+    event::EventReader,
+    system::{ResMut, Resource},
+    world::World,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{BuildWorldChildren, HierarchyEvent};
+
+/// One entry of a recorded hierarchy log.
+///
+/// Entities are referred to by ids local to the log, assigned in order of first appearance,
+/// so the log does not depend on the [`Entity`] ids of the recorded world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum HierarchyLogEntry {
+    /// An entity appeared in the log for the first time.
+    Spawned(u32),
+    /// See [`HierarchyEvent::ChildAdded`].
+    Added {
+        /// The child that was added
+        child: u32,
+        /// The parent the child was added to
+        parent: u32,
+    },
+    /// See [`HierarchyEvent::ChildRemoved`].
+    Removed {
+        /// The child that was removed
+        child: u32,
+        /// The parent the child was removed from
+        parent: u32,
+    },
+    /// See [`HierarchyEvent::ChildMoved`].
+    Moved {
+        /// The child that was moved
+        child: u32,
+        /// The parent the child was removed from
+        previous_parent: u32,
+        /// The parent the child was added to
+        new_parent: u32,
+    },
+    /// See [`HierarchyEvent::SubtreeDespawned`].
+    Despawned(Vec<u32>),
+}
+
+/// Records [`HierarchyEvent`]s as a log of [`HierarchyLogEntry`]s.
+///
+/// Add [`record_hierarchy_events`] to a schedule to fill it, or call [`HierarchyRecorder::record`].
+/// Entities that appear in an event are logged as spawned the first time they are seen, so
+/// entities that are never part of an edge are not recorded. Entities despawned without a
+/// [`HierarchyEvent::SubtreeDespawned`], such as by `World::despawn`, are not either.
+#[derive(Resource, Debug, Default)]
+pub struct HierarchyRecorder {
+    log: Vec<HierarchyLogEntry>,
+    ids: BTreeMap<Entity, u32>,
+    next_id: u32,
+}
+
+impl HierarchyRecorder {
+    /// Appends the entries for `event` to the log.
+    pub fn record(&mut self, event: &HierarchyEvent) {
+        let entry = match event {
+            &HierarchyEvent::ChildAdded { child, parent } => HierarchyLogEntry::Added {
+                child: self.id_or_spawn(child),
+                parent: self.id_or_spawn(parent),
+            },
+            &HierarchyEvent::ChildRemoved { child, parent } => HierarchyLogEntry::Removed {
+                child: self.id_or_spawn(child),
+                parent: self.id_or_spawn(parent),
+            },
+            &HierarchyEvent::ChildMoved {
+                child,
+                previous_parent,
+                new_parent,
+            } => HierarchyLogEntry::Moved {
+                child: self.id_or_spawn(child),
+                previous_parent: self.id_or_spawn(previous_parent),
+                new_parent: self.id_or_spawn(new_parent),
+            },
+            HierarchyEvent::SubtreeDespawned { entities, .. } => HierarchyLogEntry::Despawned(
+                entities
+                    .iter()
+                    .filter_map(|entity| self.ids.remove(entity))
+                    .collect(),
+            ),
+        };
+        self.log.push(entry);
+    }
+
+    fn id_or_spawn(&mut self, entity: Entity) -> u32 {
+        if let Some(&id) = self.ids.get(&entity) {
+            return id;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.ids.insert(entity, id);
+        self.log.push(HierarchyLogEntry::Spawned(id));
+        id
+    }
+
+    /// The log id of a recorded entity that was not despawned since.
+    pub fn id(&self, entity: Entity) -> Option<u32> {
+        self.ids.get(&entity).copied()
+    }
+
+    /// The entries recorded so far.
+    pub fn log(&self) -> &[HierarchyLogEntry] {
+        &self.log
+    }
+
+    /// Takes the entries recorded so far, keeping the ids of the recorded entities.
+    ///
+    /// Useful to send the log in batches, to be applied by a single [`HierarchyReplayer`].
+    pub fn drain(&mut self) -> Vec<HierarchyLogEntry> {
+        std::mem::take(&mut self.log)
+    }
+}
+
+/// System that records every [`HierarchyEvent`] in the [`HierarchyRecorder`] resource.
+pub fn record_hierarchy_events(
+    mut recorder: ResMut<HierarchyRecorder>,
+    mut events: EventReader<HierarchyEvent>,
+) {
+    for event in events.iter() {
+        recorder.record(event);
+    }
+}
+
+/// Applies logs recorded by a [`HierarchyRecorder`] to a [`World`].
+///
+/// Keeps the mapping from log ids to entities between calls to [`HierarchyReplayer::apply`],
+/// so a log can be applied in several batches.
+#[derive(Debug, Default)]
+pub struct HierarchyReplayer {
+    entities: BTreeMap<u32, Entity>,
+}
+
+impl HierarchyReplayer {
+    /// Applies `entries` to `world` through [`BuildWorldChildren`], spawning a fresh entity for
+    /// each [`HierarchyLogEntry::Spawned`]. Entries referring to unknown ids are skipped.
+    pub fn apply(&mut self, world: &mut World, entries: &[HierarchyLogEntry]) {
+        for entry in entries {
+            match entry {
+                &HierarchyLogEntry::Spawned(id) => {
+                    self.entities.insert(id, world.spawn_empty().id());
+                }
+                &HierarchyLogEntry::Added { child, parent } => {
+                    if let (Some(child), Some(parent)) = (self.entity(child), self.entity(parent)) {
+                        world.entity_mut(parent).push_children(&[child]);
+                    }
+                }
+                &HierarchyLogEntry::Removed { child, parent } => {
+                    if let (Some(child), Some(parent)) = (self.entity(child), self.entity(parent)) {
+                        world.entity_mut(parent).remove_children(&[child]);
+                    }
+                }
+                &HierarchyLogEntry::Moved {
+                    child,
+                    previous_parent,
+                    new_parent,
+                } => {
+                    if let (Some(child), Some(previous_parent), Some(new_parent)) = (
+                        self.entity(child),
+                        self.entity(previous_parent),
+                        self.entity(new_parent),
+                    ) {
+                        world
+                            .entity_mut(previous_parent)
+                            .move_child(new_parent, child);
+                    }
+                }
+                HierarchyLogEntry::Despawned(ids) => {
+                    // The edges were already removed by the preceding `Removed` entries.
+                    for id in ids {
+                        if let Some(entity) = self.entities.remove(id) {
+                            world.despawn(entity);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The entity spawned for a log id, if it was not despawned since.
+    pub fn entity(&self, id: u32) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bevy_ecs::{event::Events, world::World};
+
+    use super::{HierarchyRecorder, HierarchyReplayer};
+    use crate::{
+        assert_hierarchy_eq, BuildWorldChildren, DespawnRecursiveExt, HierarchyEvent, HierarchySpec,
+    };
+
+    fn record(world: &mut World, recorder: &mut HierarchyRecorder) {
+        for event in world.resource_mut::<Events<HierarchyEvent>>().drain() {
+            recorder.record(&event);
+        }
+    }
+
+    #[test]
+    fn replay() {
+        let mut world = World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+        let mut recorder = HierarchyRecorder::default();
+        let mut replayed = World::new();
+        let mut replayer = HierarchyReplayer::default();
+
+        let spec: HierarchySpec = "a -> b, c; b, c -> d; e -> f".parse().unwrap();
+        let mut entities = spec.spawn(&mut world);
+        record(&mut world, &mut recorder);
+        replayer.apply(&mut replayed, &recorder.drain());
+
+        world
+            .entity_mut(entities["e"])
+            .move_child(entities["c"], entities["f"]);
+        world.entity_mut(entities["b"]).despawn_recursive();
+        let mut g = None;
+        world.entity_mut(entities["a"]).with_children(|parent| {
+            g = Some(parent.spawn_empty().id());
+        });
+        entities.insert("g".to_owned(), g.unwrap());
+        record(&mut world, &mut recorder);
+        let log = recorder.drain();
+        replayer.apply(&mut replayed, &log);
+
+        entities.remove("b");
+        entities.remove("d");
+        assert_hierarchy_eq!(world, entities, "a -> c, g; c -> f; e");
+        assert_eq!(replayed.entities().len(), 5);
+        let replayed_entities = entities
+            .iter()
+            .map(|(name, &entity)| {
+                let id = recorder.id(entity).unwrap();
+                (name.clone(), replayer.entity(id).unwrap())
+            })
+            .collect::<BTreeMap<_, _>>();
+        assert_hierarchy_eq!(replayed, replayed_entities, "a -> c, g; c -> f; e");
+
+        #[cfg(feature = "serde")]
+        {
+            let document = ron::to_string(&log).unwrap();
+            let loaded: Vec<super::HierarchyLogEntry> = ron::from_str(&document).unwrap();
+            assert_eq!(loaded, log);
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use bevy_ecs::{entity::Entity, world::World};
+use serde::{Deserialize, Serialize};
+
+use crate::{hierarchy::collect_hierarchy, BuildWorldChildren, Children};
+
+/// A portable copy of the edges of a hierarchy, independent of [`Entity`] ids.
+///
+/// Nodes are identified by their index in `0..nodes`. An entity with several parents is stored
+/// once, with one edge per parent, so shared children stay shared when the snapshot is spawned.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HierarchySnapshot {
+    /// The number of nodes in the snapshot.
+    pub nodes: usize,
+    /// The `(parent, child)` node index pairs, sorted.
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// An error returned when spawning a [`HierarchySnapshot`] with an edge to a node index out of
+/// `0..nodes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSnapshotEdgeError {
+    /// The `(parent, child)` node index pair of the edge.
+    pub edge: (usize, usize),
+    /// The number of nodes of the snapshot.
+    pub nodes: usize,
+}
+
+impl fmt::Display for InvalidSnapshotEdgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "edge {:?} refers to a node out of the {} nodes of the snapshot",
+            self.edge, self.nodes
+        )
+    }
+}
+
+impl std::error::Error for InvalidSnapshotEdgeError {}
+
+impl HierarchySnapshot {
+    /// Takes a snapshot of every entity with a [`Parents`] or [`Children`] component.
+    ///
+    /// Returns the snapshot along with the entity of each node, by index.
+    ///
+    /// [`Parents`]: crate::Parents
+    pub fn from_world(world: &World) -> (Self, Vec<Entity>) {
+        let entities = collect_hierarchy(world, None);
+        Self::from_entities(world, entities.into_iter().collect())
+    }
+
+    /// Takes a snapshot of `roots` and all of their descendants.
+    ///
+    /// Edges from parents outside of the subgraph are left out.
+    /// Returns the snapshot along with the entity of each node, by index.
+    /// Nodes are numbered breadth-first, starting with `roots` in order.
+    pub fn from_roots(world: &World, roots: &[Entity]) -> (Self, Vec<Entity>) {
+        let mut visited = BTreeSet::new();
+        let mut entities = Vec::new();
+        let mut index = 0;
+        entities.extend(roots.iter().filter(|root| visited.insert(**root)));
+        while let Some(&entity) = entities.get(index) {
+            index += 1;
+            let children = world.get::<Children>(entity).into_iter().flatten();
+            entities.extend(children.filter(|child| visited.insert(**child)));
+        }
+        Self::from_entities(world, entities)
+    }
+
+    fn from_entities(world: &World, entities: Vec<Entity>) -> (Self, Vec<Entity>) {
+        let indices = entities
+            .iter()
+            .enumerate()
+            .map(|(index, entity)| (*entity, index))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut edges = Vec::new();
+        for (parent_index, &parent) in entities.iter().enumerate() {
+            for child in world.get::<Children>(parent).into_iter().flatten() {
+                if let Some(&child_index) = indices.get(child) {
+                    edges.push((parent_index, child_index));
+                }
+            }
+        }
+        edges.sort_unstable();
+
+        let snapshot = HierarchySnapshot {
+            nodes: entities.len(),
+            edges,
+        };
+        (snapshot, entities)
+    }
+
+    /// Spawns one fresh entity per node and connects them as described by the snapshot.
+    ///
+    /// Sends a [`HierarchyEvent::ChildAdded`] per edge.
+    /// Returns the entity of each node, by index.
+    ///
+    /// Nothing is spawned if an edge refers to a node index out of `0..nodes`, as can happen with
+    /// a document that was edited by hand.
+    ///
+    /// [`HierarchyEvent::ChildAdded`]: crate::HierarchyEvent::ChildAdded
+    pub fn spawn(&self, world: &mut World) -> Result<Vec<Entity>, InvalidSnapshotEdgeError> {
+        if let Some(&edge) = self
+            .edges
+            .iter()
+            .find(|(parent, child)| *parent >= self.nodes || *child >= self.nodes)
+        {
+            return Err(InvalidSnapshotEdgeError {
+                edge,
+                nodes: self.nodes,
+            });
+        }
+
+        let entities = (0..self.nodes)
+            .map(|_| world.spawn_empty().id())
+            .collect::<Vec<_>>();
+
+        let mut children = BTreeMap::<usize, Vec<Entity>>::new();
+        for &(parent, child) in &self.edges {
+            children.entry(parent).or_default().push(entities[child]);
+        }
+        for (parent, children) in children {
+            world.entity_mut(entities[parent]).push_children(&children);
+        }
+        Ok(entities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::World;
+
+    use super::{HierarchySnapshot, InvalidSnapshotEdgeError};
+    use crate::{BuildWorldChildren, Children, Parents};
+
+    #[test]
+    fn snapshot_round_trip() {
+        let world = &mut World::new();
+        let [a, b, c, d, e, f] = std::array::from_fn(|_| world.spawn_empty().id());
+
+        // a -> b, c; b -> d; c -> d; e -> f
+        world.entity_mut(a).push_children(&[b, c]);
+        world.entity_mut(b).push_children(&[d]);
+        world.entity_mut(c).push_children(&[d]);
+        world.entity_mut(e).push_children(&[f]);
+
+        let (snapshot, entities) = HierarchySnapshot::from_roots(world, &[a]);
+        assert_eq!(entities, [a, b, c, d]);
+        assert_eq!(snapshot.nodes, 4);
+        assert_eq!(snapshot.edges, [(0, 1), (0, 2), (1, 3), (2, 3)]);
+
+        let document = ron::to_string(&snapshot).unwrap();
+        let loaded: HierarchySnapshot = ron::from_str(&document).unwrap();
+        assert_eq!(loaded, snapshot);
+
+        let other = &mut World::new();
+        other.spawn_empty();
+        let [a, b, c, d] = <[_; 4]>::try_from(loaded.spawn(other).unwrap()).unwrap();
+        assert_eq!(other.get::<Children>(a).unwrap().to_vec(), [b, c]);
+        assert_eq!(other.get::<Parents>(d).unwrap().to_vec(), [b, c]);
+
+        let (whole, entities) = HierarchySnapshot::from_world(world);
+        assert_eq!(entities.len(), 6);
+        assert_eq!(whole.edges.len(), 5);
+    }
+
+    #[test]
+    fn invalid_edge() {
+        let world = &mut World::new();
+        let loaded: HierarchySnapshot =
+            ron::from_str("(nodes: 2, edges: [(0, 1), (1, 2)])").unwrap();
+        assert_eq!(
+            loaded.spawn(world),
+            Err(InvalidSnapshotEdgeError {
+                edge: (1, 2),
+                nodes: 2,
+            })
+        );
+        assert_eq!(world.entities().len(), 0);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy_ecs::{
+    entity::Entity,
+    event::EventReader,
+    system::{Query, SystemParam},
+};
+
+use crate::{HierarchyEvent, Parents};
+
+/// A [`SystemParam`] that reads only the [`HierarchyEvent`]s concerning the subtree of a root.
+///
+/// An event is yielded if any entity it refers to is the root or one of its descendants,
+/// as resolved against the current [`Parents`] components. Despawned entities no longer have
+/// [`Parents`], so a child removed from the subtree by a [`HierarchyEvent::ChildRemoved`] is still
+/// treated as part of it for the events read after, such as the other events of a recursive
+/// despawn.
+///
+/// # Examples
+/// ```
+/// # use bevy_ecs::prelude::*;
+/// # use bevy_parents_childs::SubtreeEventReader;
+/// # #[derive(Component)]
+/// # struct Panel;
+/// fn system(panel: Query<Entity, With<Panel>>, mut events: SubtreeEventReader) {
+///     let root = panel.single();
+///     for event in events.iter(root) {
+///         // Do something!
+///     }
+/// }
+/// # bevy_ecs::system::assert_is_system(system);
+/// ```
+#[derive(SystemParam)]
+pub struct SubtreeEventReader<'w, 's> {
+    events: EventReader<'w, 's, HierarchyEvent>,
+    parents: Query<'w, 's, &'static Parents>,
+}
+
+impl<'w, 's> SubtreeEventReader<'w, 's> {
+    /// Iterates over the events this [`SubtreeEventReader`] has not seen yet, skipping those
+    /// outside the subtree of `root`.
+    ///
+    /// Like [`EventReader::iter`], this marks every event as read, including skipped ones.
+    pub fn iter(&mut self, root: Entity) -> impl Iterator<Item = &HierarchyEvent> + '_ {
+        let parents = &self.parents;
+        let mut cache = BTreeMap::new();
+        let mut removed = BTreeSet::new();
+        self.events.iter().filter(move |event| {
+            let mut inside = |entity: Entity| {
+                removed.contains(&entity) || is_in_subtree(parents, &mut cache, root, entity)
+            };
+            match **event {
+                HierarchyEvent::ChildRemoved { child, parent } if inside(parent) => {
+                    removed.insert(child);
+                    true
+                }
+                _ => event_entities(event).any(inside),
+            }
+        })
+    }
+
+    /// Consumes all unread events, including those outside any subtree.
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// The entities whose position in the hierarchy an event describes.
+fn event_entities(event: &HierarchyEvent) -> impl Iterator<Item = Entity> {
+    match *event {
+        HierarchyEvent::ChildAdded { child, parent }
+        | HierarchyEvent::ChildRemoved { child, parent } => vec![child, parent],
+        HierarchyEvent::ChildMoved {
+            child,
+            previous_parent,
+            new_parent,
+        } => vec![child, previous_parent, new_parent],
+        HierarchyEvent::SubtreeDespawned { root, ref entities } => {
+            let mut entities = entities.clone();
+            entities.push(root);
+            entities
+        }
+    }
+    .into_iter()
+}
+
+/// Returns whether `entity` is `root` or one of its descendants.
+///
+/// Walks up [`Parents`] depth-first. Since a negative answer visits every ancestor of
+/// `entity`, all of them are cached as outside the subtree as well.
+fn is_in_subtree(
+    parents: &Query<&Parents>,
+    cache: &mut BTreeMap<Entity, bool>,
+    root: Entity,
+    entity: Entity,
+) -> bool {
+    if entity == root {
+        return true;
+    }
+    if let Some(&cached) = cache.get(&entity) {
+        return cached;
+    }
+
+    let mut visited = BTreeSet::from([entity]);
+    let mut nexts = vec![entity];
+    while let Some(node) = nexts.pop() {
+        for &parent in parents.get(node).into_iter().flatten() {
+            if parent == root || cache.get(&parent) == Some(&true) {
+                cache.insert(entity, true);
+                return true;
+            }
+            if cache.get(&parent) == Some(&false) {
+                continue;
+            }
+            if visited.insert(parent) {
+                nexts.push(parent);
+            }
+        }
+    }
+
+    cache.extend(visited.into_iter().map(|node| (node, false)));
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{event::Events, system::SystemState, world::World};
+
+    use super::SubtreeEventReader;
+    use crate::{
+        BuildWorldChildren, DespawnRecursiveExt,
+        HierarchyEvent::{self, ChildAdded, ChildMoved, ChildRemoved, SubtreeDespawned},
+    };
+
+    #[test]
+    fn subtree_event_reader() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d, e] = std::array::from_fn(|_| world.spawn_empty().id());
+        let mut system_state = SystemState::<SubtreeEventReader>::new(world);
+
+        // a -> b -> c; d -> e
+        world.entity_mut(a).push_children(&[b]);
+        world.entity_mut(b).push_children(&[c]);
+        world.entity_mut(d).push_children(&[e]);
+
+        let mut reader = system_state.get_mut(world);
+        let events: Vec<_> = reader.iter(b).cloned().collect();
+        assert_eq!(
+            events,
+            [
+                ChildAdded {
+                    child: b,
+                    parent: a,
+                },
+                ChildAdded {
+                    child: c,
+                    parent: b,
+                },
+            ]
+        );
+
+        // Events are consumed even when they are outside the subtree.
+        assert_eq!(reader.iter(d).count(), 0);
+
+        // Moving `e` under `c` concerns `b`'s subtree through the new parent.
+        world.entity_mut(d).move_child(c, e);
+        world.entity_mut(d).push_children(&[a]);
+
+        let mut reader = system_state.get_mut(world);
+        let events: Vec<_> = reader.iter(b).cloned().collect();
+        assert_eq!(
+            events,
+            [ChildMoved {
+                child: e,
+                previous_parent: d,
+                new_parent: c,
+            }]
+        );
+    }
+
+    #[test]
+    fn despawned_descendant() {
+        let world = &mut World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+
+        let [a, b, c, d] = std::array::from_fn(|_| world.spawn_empty().id());
+        let mut system_state = SystemState::<SubtreeEventReader>::new(world);
+
+        // a -> b -> c -> d
+        world.entity_mut(a).push_children(&[b]);
+        world.entity_mut(b).push_children(&[c]);
+        world.entity_mut(c).push_children(&[d]);
+        system_state.get_mut(world).clear();
+
+        world.entity_mut(c).despawn_recursive();
+
+        let mut reader = system_state.get_mut(world);
+        let events: Vec<_> = reader.iter(a).cloned().collect();
+        assert_eq!(
+            events,
+            [
+                ChildRemoved {
+                    child: c,
+                    parent: b,
+                },
+                // `c` was despawned, but it still counts as a descendant of `a`.
+                ChildRemoved {
+                    child: d,
+                    parent: c,
+                },
+                SubtreeDespawned {
+                    root: c,
+                    entities: vec![c, d],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,565 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use bevy_ecs::{
+    entity::Entity,
+    event::{Event, Events},
+    system::Command,
+    world::World,
+};
+use bevy_utils::tracing::warn;
+
+use crate::{despawn_with_children_recursive, BuildWorldChildren, Children};
+
+/// A single edit of a [`HierarchyTransaction`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HierarchyOp {
+    /// Adds `child` to `parent`, as by [`BuildWorldChildren::push_children`].
+    AddChild {
+        /// The parent to add the child to
+        parent: Entity,
+        /// The child to add
+        child: Entity,
+    },
+    /// Removes `child` from `parent`, as by [`BuildWorldChildren::remove_children`].
+    RemoveChild {
+        /// The parent to remove the child from
+        parent: Entity,
+        /// The child to remove
+        child: Entity,
+    },
+    /// Moves `child` from `parent` to `new_parent`, as by [`BuildWorldChildren::move_child`].
+    MoveChild {
+        /// The parent to move the child from
+        parent: Entity,
+        /// The child to move
+        child: Entity,
+        /// The parent to move the child to
+        new_parent: Entity,
+    },
+    /// Despawns an entity, keeping its children alive, as by [`BuildWorldChildren::clear`].
+    Despawn(Entity),
+    /// Despawns an entity and its descendants, as by [`despawn_with_children_recursive`].
+    DespawnRecursive(Entity),
+}
+
+/// The reason a [`HierarchyTransaction`] was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HierarchyTransactionError {
+    /// The op at index `op` refers to an entity that does not exist at that point of the
+    /// transaction.
+    MissingEntity {
+        /// Index of the op in [`HierarchyTransaction::ops`]
+        op: usize,
+        /// The missing entity
+        entity: Entity,
+    },
+    /// The edge added by the op at index `op` is part of a cycle once the transaction is applied.
+    Cycle {
+        /// Index of the op in [`HierarchyTransaction::ops`]
+        op: usize,
+        /// Parent of the edge
+        parent: Entity,
+        /// Child of the edge
+        child: Entity,
+    },
+}
+
+impl fmt::Display for HierarchyTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HierarchyTransactionError::MissingEntity { op, entity } => {
+                write!(f, "op {op} refers to missing entity {entity:?}")
+            }
+            HierarchyTransactionError::Cycle { op, parent, child } => {
+                write!(
+                    f,
+                    "op {op} adds edge {parent:?} -> {child:?}, creating a cycle"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for HierarchyTransactionError {}
+
+/// An [`Event`] sent when a [`HierarchyTransaction`] command is rejected.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyTransactionFailed {
+    /// Why the transaction was rejected
+    pub error: HierarchyTransactionError,
+}
+
+/// Command that applies a batch of hierarchy edits atomically.
+///
+/// The whole batch is checked before anything is applied: every op must refer to entities that
+/// exist at that point of the batch, and the edges it adds must not create cycles. If a check
+/// fails, the world is left untouched, a warning is logged and a [`HierarchyTransactionFailed`]
+/// event is sent. Cycles that already existed before the transaction are not reported.
+///
+/// See [`apply_hierarchy_transaction`] to get the error back directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HierarchyTransaction {
+    /// The edits, applied in order.
+    pub ops: Vec<HierarchyOp>,
+}
+
+impl HierarchyTransaction {
+    /// Appends a [`HierarchyOp::AddChild`].
+    pub fn add_child(mut self, parent: Entity, child: Entity) -> Self {
+        self.ops.push(HierarchyOp::AddChild { parent, child });
+        self
+    }
+
+    /// Appends a [`HierarchyOp::RemoveChild`].
+    pub fn remove_child(mut self, parent: Entity, child: Entity) -> Self {
+        self.ops.push(HierarchyOp::RemoveChild { parent, child });
+        self
+    }
+
+    /// Appends a [`HierarchyOp::MoveChild`].
+    pub fn move_child(mut self, parent: Entity, child: Entity, new_parent: Entity) -> Self {
+        self.ops.push(HierarchyOp::MoveChild {
+            parent,
+            child,
+            new_parent,
+        });
+        self
+    }
+
+    /// Appends a [`HierarchyOp::Despawn`].
+    pub fn despawn(mut self, entity: Entity) -> Self {
+        self.ops.push(HierarchyOp::Despawn(entity));
+        self
+    }
+
+    /// Appends a [`HierarchyOp::DespawnRecursive`].
+    pub fn despawn_recursive(mut self, entity: Entity) -> Self {
+        self.ops.push(HierarchyOp::DespawnRecursive(entity));
+        self
+    }
+}
+
+impl Command for HierarchyTransaction {
+    fn apply(self, world: &mut World) {
+        #[cfg(feature = "trace")]
+        let _span = bevy_utils::tracing::info_span!(
+            "command",
+            name = "HierarchyTransaction",
+            ops = self.ops.len()
+        )
+        .entered();
+        if let Err(error) = apply_hierarchy_transaction(world, &self.ops) {
+            warn!("Hierarchy transaction rejected: {error}");
+            if let Some(mut events) = world.get_resource_mut::<Events<HierarchyTransactionFailed>>()
+            {
+                events.send(HierarchyTransactionFailed { error });
+            }
+        }
+    }
+}
+
+/// Function for applying a batch of hierarchy edits atomically.
+///
+/// Returns the first failed check without changing the world. See [`HierarchyTransaction`].
+pub fn apply_hierarchy_transaction(
+    world: &mut World,
+    ops: &[HierarchyOp],
+) -> Result<(), HierarchyTransactionError> {
+    validate(world, ops)?;
+    for op in ops {
+        match *op {
+            HierarchyOp::AddChild { parent, child } => {
+                world.entity_mut(parent).push_children(&[child]);
+            }
+            HierarchyOp::RemoveChild { parent, child } => {
+                world.entity_mut(parent).remove_children(&[child]);
+            }
+            HierarchyOp::MoveChild {
+                parent,
+                child,
+                new_parent,
+            } => {
+                world.entity_mut(parent).move_child(new_parent, child);
+            }
+            HierarchyOp::Despawn(entity) => world.entity_mut(entity).clear(),
+            HierarchyOp::DespawnRecursive(entity) => {
+                despawn_with_children_recursive(world, entity);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The edges of `world` with the ops validated so far applied on top.
+struct Overlay<'w> {
+    world: &'w World,
+    added: BTreeMap<(Entity, Entity), usize>,
+    removed: BTreeSet<(Entity, Entity)>,
+    despawned: BTreeSet<Entity>,
+}
+
+impl Overlay<'_> {
+    fn exists(&self, entity: Entity) -> bool {
+        self.world.get_entity(entity).is_some() && !self.despawned.contains(&entity)
+    }
+
+    fn children(&self, parent: Entity) -> Vec<Entity> {
+        let existing = self
+            .world
+            .get::<Children>(parent)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|child| !self.removed.contains(&(parent, *child)));
+        let added = self
+            .added
+            .keys()
+            .filter(|(p, _)| *p == parent)
+            .map(|(_, child)| *child);
+        existing
+            .chain(added)
+            .filter(|child| !self.despawned.contains(child))
+            .collect()
+    }
+
+    fn add(&mut self, op: usize, parent: Entity, child: Entity) {
+        if !self.children(parent).contains(&child) {
+            self.removed.remove(&(parent, child));
+            self.added.insert((parent, child), op);
+        }
+    }
+
+    fn remove(&mut self, parent: Entity, child: Entity) {
+        self.added.remove(&(parent, child));
+        self.removed.insert((parent, child));
+    }
+
+    fn reaches(&self, from: Entity, to: Entity) -> bool {
+        let mut visited = BTreeSet::from([from]);
+        let mut stack = vec![from];
+        while let Some(entity) = stack.pop() {
+            if entity == to {
+                return true;
+            }
+            for child in self.children(entity) {
+                if visited.insert(child) {
+                    stack.push(child);
+                }
+            }
+        }
+        false
+    }
+}
+
+fn validate(world: &World, ops: &[HierarchyOp]) -> Result<(), HierarchyTransactionError> {
+    let mut overlay = Overlay {
+        world,
+        added: BTreeMap::new(),
+        removed: BTreeSet::new(),
+        despawned: BTreeSet::new(),
+    };
+    for (index, op) in ops.iter().enumerate() {
+        let entities = match *op {
+            HierarchyOp::AddChild { parent, child }
+            | HierarchyOp::RemoveChild { parent, child } => {
+                vec![parent, child]
+            }
+            HierarchyOp::MoveChild {
+                parent,
+                child,
+                new_parent,
+            } => vec![parent, child, new_parent],
+            HierarchyOp::Despawn(entity) | HierarchyOp::DespawnRecursive(entity) => vec![entity],
+        };
+        if let Some(&entity) = entities.iter().find(|entity| !overlay.exists(**entity)) {
+            return Err(HierarchyTransactionError::MissingEntity { op: index, entity });
+        }
+
+        match *op {
+            HierarchyOp::AddChild { parent, child } => overlay.add(index, parent, child),
+            HierarchyOp::RemoveChild { parent, child } => overlay.remove(parent, child),
+            HierarchyOp::MoveChild {
+                parent,
+                child,
+                new_parent,
+            } => {
+                if parent != new_parent {
+                    overlay.remove(parent, child);
+                    overlay.add(index, new_parent, child);
+                }
+            }
+            HierarchyOp::Despawn(entity) => {
+                overlay.despawned.insert(entity);
+            }
+            HierarchyOp::DespawnRecursive(entity) => {
+                let mut stack = vec![entity];
+                while let Some(entity) = stack.pop() {
+                    if overlay.despawned.insert(entity) {
+                        stack.extend(overlay.children(entity));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut added = overlay
+        .added
+        .iter()
+        .map(|(&edge, &op)| (op, edge))
+        .collect::<Vec<_>>();
+    added.sort_unstable();
+    for (op, (parent, child)) in added {
+        if overlay.exists(parent) && overlay.exists(child) && overlay.reaches(child, parent) {
+            return Err(HierarchyTransactionError::Cycle { op, parent, child });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        event::Events,
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+
+    use super::{
+        apply_hierarchy_transaction, HierarchyTransaction, HierarchyTransactionError,
+        HierarchyTransactionFailed,
+    };
+    use crate::{assert_hierarchy_eq, HierarchyEvent, HierarchySpec};
+
+    #[test]
+    fn transaction() {
+        let mut world = World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+        world.insert_resource(Events::<HierarchyTransactionFailed>::default());
+        let spec: HierarchySpec = "a -> x; b; c; d -> e".parse().unwrap();
+        let mut entities = spec.spawn(&mut world);
+        let [a, b, c, d, x] = ["a", "b", "c", "d", "x"].map(|name| entities[name]);
+
+        let transaction = HierarchyTransaction::default()
+            .remove_child(a, x)
+            .add_child(b, x)
+            .add_child(c, x)
+            .despawn(d);
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world).add(transaction);
+        queue.apply(&mut world);
+
+        entities.remove("d");
+        assert_hierarchy_eq!(world, entities, "a; b, c -> x; e");
+        assert!(world.get_entity(d).is_none());
+
+        // The second op refers to `d`, so nothing is applied.
+        let ops = HierarchyTransaction::default()
+            .move_child(b, x, a)
+            .add_child(d, x)
+            .ops;
+        assert_eq!(
+            apply_hierarchy_transaction(&mut world, &ops),
+            Err(HierarchyTransactionError::MissingEntity { op: 1, entity: d })
+        );
+        // Despawning `b` recursively despawns `x` for the next op.
+        let ops = HierarchyTransaction::default()
+            .despawn_recursive(b)
+            .add_child(a, x)
+            .ops;
+        assert_eq!(
+            apply_hierarchy_transaction(&mut world, &ops),
+            Err(HierarchyTransactionError::MissingEntity { op: 1, entity: x })
+        );
+        assert_hierarchy_eq!(world, entities, "a; b, c -> x; e");
+
+        world.resource_mut::<Events<HierarchyEvent>>().clear();
+        let transaction = HierarchyTransaction::default()
+            .add_child(x, a)
+            .move_child(c, x, a);
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world).add(transaction);
+        queue.apply(&mut world);
+
+        assert_hierarchy_eq!(world, entities, "a; b, c -> x; e");
+        assert!(world.resource::<Events<HierarchyEvent>>().is_empty());
+        let failures = world
+            .resource_mut::<Events<HierarchyTransactionFailed>>()
+            .drain()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failures,
+            [HierarchyTransactionFailed {
+                error: HierarchyTransactionError::Cycle {
+                    op: 0,
+                    parent: x,
+                    child: a,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_entity_at_op() {
+        let mut world = World::new();
+        let entities = "a -> b; c"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        let [a, b, c] = ["a", "b", "c"].map(|name| entities[name]);
+        let gone = world.spawn_empty().id();
+        world.despawn(gone);
+
+        let ops = HierarchyTransaction::default()
+            .remove_child(a, b)
+            .add_child(c, b)
+            .move_child(c, b, gone)
+            .ops;
+        assert_eq!(
+            apply_hierarchy_transaction(&mut world, &ops),
+            Err(HierarchyTransactionError::MissingEntity {
+                op: 2,
+                entity: gone
+            })
+        );
+
+        // An entity despawned by an earlier op is missing for the later ones.
+        let ops = HierarchyTransaction::default()
+            .add_child(c, b)
+            .despawn(c)
+            .remove_child(c, b)
+            .ops;
+        assert_eq!(
+            apply_hierarchy_transaction(&mut world, &ops),
+            Err(HierarchyTransactionError::MissingEntity { op: 2, entity: c })
+        );
+        assert_hierarchy_eq!(world, entities, "a -> b; c");
+    }
+
+    #[test]
+    fn cycle_from_later_op() {
+        let mut world = World::new();
+        let entities = "a -> b; c"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        let [a, b, c] = ["a", "b", "c"].map(|name| entities[name]);
+
+        // The first op is fine, the second closes `a -> b -> a`.
+        let ops = HierarchyTransaction::default()
+            .add_child(a, c)
+            .add_child(b, a)
+            .ops;
+        assert_eq!(
+            apply_hierarchy_transaction(&mut world, &ops),
+            Err(HierarchyTransactionError::Cycle {
+                op: 1,
+                parent: b,
+                child: a,
+            })
+        );
+        // When several new edges form the cycle, the first of them is reported.
+        let ops = HierarchyTransaction::default()
+            .add_child(b, c)
+            .add_child(a, c)
+            .add_child(c, a)
+            .ops;
+        assert_eq!(
+            apply_hierarchy_transaction(&mut world, &ops),
+            Err(HierarchyTransactionError::Cycle {
+                op: 0,
+                parent: b,
+                child: c,
+            })
+        );
+        assert_hierarchy_eq!(world, entities, "a -> b; c");
+
+        // A cycle broken by a later op is accepted.
+        let ops = HierarchyTransaction::default()
+            .add_child(b, a)
+            .remove_child(a, b)
+            .ops;
+        assert_eq!(apply_hierarchy_transaction(&mut world, &ops), Ok(()));
+        assert_hierarchy_eq!(world, entities, "b -> a; c");
+    }
+
+    #[test]
+    fn failed_transaction_changes_nothing() {
+        let mut world = World::new();
+        world.insert_resource(Events::<HierarchyEvent>::default());
+        world.insert_resource(Events::<HierarchyTransactionFailed>::default());
+        let entities = "a -> b, c; c -> d"
+            .parse::<HierarchySpec>()
+            .unwrap()
+            .spawn(&mut world);
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| entities[name]);
+        world.resource_mut::<Events<HierarchyEvent>>().clear();
+
+        // Every op but the last is valid, and would be applied without it.
+        let transaction = HierarchyTransaction::default()
+            .move_child(a, b, c)
+            .despawn(d)
+            .despawn_recursive(c)
+            .add_child(b, a);
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world).add(transaction);
+        queue.apply(&mut world);
+
+        assert_hierarchy_eq!(world, entities, "a -> b, c; c -> d");
+        assert!(world.get_entity(c).is_some());
+        assert!(world.get_entity(d).is_some());
+        assert!(world.resource::<Events<HierarchyEvent>>().is_empty());
+        assert_eq!(
+            world
+                .resource_mut::<Events<HierarchyTransactionFailed>>()
+                .drain()
+                .collect::<Vec<_>>(),
+            [HierarchyTransactionFailed {
+                error: HierarchyTransactionError::MissingEntity { op: 3, entity: b },
+            }]
+        );
+    }
+
+    #[test]
+    fn failure_event() {
+        let mut world = World::new();
+        world.insert_resource(Events::<HierarchyTransactionFailed>::default());
+        let entities = "a; b".parse::<HierarchySpec>().unwrap().spawn(&mut world);
+        let [a, b] = ["a", "b"].map(|name| entities[name]);
+
+        // A successful transaction sends nothing.
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &world).add(HierarchyTransaction::default().add_child(a, b));
+        queue.apply(&mut world);
+        assert!(world
+            .resource::<Events<HierarchyTransactionFailed>>()
+            .is_empty());
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        commands.add(HierarchyTransaction::default().add_child(b, a));
+        commands.entity(b).despawn();
+        commands.add(HierarchyTransaction::default().remove_child(a, b));
+        queue.apply(&mut world);
+        assert_eq!(
+            world
+                .resource_mut::<Events<HierarchyTransactionFailed>>()
+                .drain()
+                .collect::<Vec<_>>(),
+            [
+                HierarchyTransactionFailed {
+                    error: HierarchyTransactionError::Cycle {
+                        op: 0,
+                        parent: b,
+                        child: a,
+                    },
+                },
+                HierarchyTransactionFailed {
+                    error: HierarchyTransactionError::MissingEntity { op: 0, entity: b },
+                },
+            ]
+        );
+    }
+}
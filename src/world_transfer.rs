@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy_ecs::{entity::Entity, reflect::AppTypeRegistry, world::World};
+
+use crate::{
+    clone_subtree::{insert_reflected, map_copied_entities, reflect_components, restore_entities},
+    hierarchy::collect_subtree,
+    BuildWorldChildren, Children, DespawnRecursiveExt, Parents,
+};
+
+/// A subtree moved to another [`World`] by [`extract_subtree`].
+///
+/// Remembers the edges from parents that stayed behind, so that [`merge_subtree`] can
+/// reconnect the subtree when moving it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtractedSubtree {
+    /// The root of the subtree, in the world it was extracted to.
+    pub root: Entity,
+    /// For entities of the subtree, in the world it was extracted to, their parents outside of
+    /// the subtree, in the world it was extracted from.
+    pub outside_parents: BTreeMap<Entity, Vec<Entity>>,
+    /// For the dead entities that replaced references to entities outside of the subtree, in the
+    /// world it was extracted to, the entity they replaced, in the world it was extracted from.
+    pub outside_references: BTreeMap<Entity, Entity>,
+}
+
+/// The result of copying a subtree to another [`World`].
+struct Transfer {
+    /// The copy of each entity.
+    copies: BTreeMap<Entity, Entity>,
+    /// The parents outside of the subtree of each entity.
+    outside_parents: BTreeMap<Entity, Vec<Entity>>,
+    /// The entity replaced by each dead entity given to an outside reference.
+    outside_references: BTreeMap<Entity, Entity>,
+}
+
+/// Moves `root` and all its descendants from `source` to `destination`.
+///
+/// Every entity is spawned once in `destination`, with its components cloned through reflection
+/// and its [`Parents`] and [`Children`] remapped to the new entities. Components that are not
+/// registered in the [`AppTypeRegistry`] of either world are lost. Entity references inside
+/// components registered with [`ReflectMapEntities`] are remapped as well, and references to
+/// entities outside of the subtree are replaced with dead entities, as when loading a
+/// `DynamicScene`, which [`merge_subtree`] maps back. The subtree is then despawned
+/// from `source`, as by [`DespawnRecursiveExt::despawn_recursive`].
+///
+/// Returns `None` if `root` does not exist in `source`.
+///
+/// [`ReflectMapEntities`]: bevy_ecs::reflect::ReflectMapEntities
+pub fn extract_subtree(
+    source: &mut World,
+    destination: &mut World,
+    root: Entity,
+) -> Option<ExtractedSubtree> {
+    source.get_entity(root)?;
+    let Transfer {
+        copies,
+        outside_parents,
+        outside_references,
+    } = transfer(source, destination, root);
+    source.entity_mut(root).despawn_recursive();
+
+    let outside_parents = outside_parents
+        .into_iter()
+        .map(|(entity, parents)| (copies[&entity], parents))
+        .collect();
+    Some(ExtractedSubtree {
+        root: copies[&root],
+        outside_parents,
+        outside_references,
+    })
+}
+
+/// Moves a subtree previously extracted by [`extract_subtree`] from `source` back to `destination`.
+///
+/// The subtree is read again from its root, so the entities and edges added to it while in
+/// `source` are moved too. Each entity gets a fresh entity in `destination`, and the copies of
+/// entities that had parents outside of the subtree are added back to those parents, if they
+/// still exist. References to entities outside of the subtree that were replaced with dead
+/// entities by [`extract_subtree`] point to the original entities again. Sends a
+/// [`HierarchyEvent::ChildAdded`] per edge in `destination`.
+///
+/// Returns the entity in `destination` of each entity of the subtree in `source`,
+/// or `None` if the root no longer exists in `source`.
+///
+/// [`HierarchyEvent::ChildAdded`]: crate::HierarchyEvent::ChildAdded
+pub fn merge_subtree(
+    source: &mut World,
+    destination: &mut World,
+    extracted: &ExtractedSubtree,
+) -> Option<BTreeMap<Entity, Entity>> {
+    source.get_entity(extracted.root)?;
+    let Transfer {
+        copies,
+        outside_references,
+        ..
+    } = transfer(source, destination, extracted.root);
+    source.entity_mut(extracted.root).despawn_recursive();
+
+    let restore = outside_references
+        .into_iter()
+        .filter_map(|(dead, entity)| Some((dead, *extracted.outside_references.get(&entity)?)))
+        .collect::<BTreeMap<_, _>>();
+    if let Some(registry) = destination.get_resource::<AppTypeRegistry>().cloned() {
+        restore_entities(destination, &registry.read(), &copies, &restore);
+    }
+
+    for (entity, parents) in &extracted.outside_parents {
+        let Some(&copy) = copies.get(entity) else {
+            continue;
+        };
+        for &parent in parents {
+            if destination.get_entity(parent).is_some() {
+                destination.entity_mut(parent).push_children(&[copy]);
+            }
+        }
+    }
+    Some(copies)
+}
+
+/// Spawns a copy of the subtree of `root` from `source` in `destination`.
+fn transfer(source: &World, destination: &mut World, root: Entity) -> Transfer {
+    let mut entities = collect_subtree(source, root);
+    // A plain despawn can leave children behind in `Children`.
+    entities.retain(|entity| source.get_entity(*entity).is_some());
+    let copies = entities
+        .iter()
+        .map(|&entity| (entity, destination.spawn_empty().id()))
+        .collect::<BTreeMap<_, _>>();
+
+    let registry = source
+        .get_resource::<AppTypeRegistry>()
+        .or_else(|| destination.get_resource::<AppTypeRegistry>())
+        .cloned();
+    let mut outside_references = BTreeMap::new();
+    if let Some(registry) = registry {
+        let registry = registry.read();
+        for &entity in &entities {
+            let components = reflect_components(source, &registry, entity);
+            insert_reflected(destination, &registry, copies[&entity], &components);
+        }
+        outside_references = map_copied_entities(destination, &registry, &copies);
+    }
+
+    let subtree = BTreeSet::from_iter(entities.iter().copied());
+    let mut outside_parents = BTreeMap::new();
+    for &entity in &entities {
+        let children = source
+            .get::<Children>(entity)
+            .into_iter()
+            .flatten()
+            .filter_map(|child| copies.get(child).copied())
+            .collect::<Vec<_>>();
+        if !children.is_empty() {
+            destination
+                .entity_mut(copies[&entity])
+                .push_children(&children);
+        }
+
+        let parents = source
+            .get::<Parents>(entity)
+            .into_iter()
+            .flatten()
+            .filter(|parent| !subtree.contains(parent))
+            .copied()
+            .collect::<Vec<_>>();
+        if !parents.is_empty() {
+            outside_parents.insert(entity, parents);
+        }
+    }
+    Transfer {
+        copies,
+        outside_parents,
+        outside_references,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{
+        component::Component,
+        entity::{Entity, EntityMapper, MapEntities},
+        reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities},
+        world::World,
+    };
+    use bevy_reflect::Reflect;
+
+    use super::{extract_subtree, merge_subtree};
+    use crate::{assert_hierarchy_eq, BuildWorldChildren, Children, HierarchySpec};
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Value(u32);
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component, MapEntities)]
+    struct Link(Entity);
+
+    impl Default for Link {
+        fn default() -> Self {
+            Link(Entity::PLACEHOLDER)
+        }
+    }
+
+    impl MapEntities for Link {
+        fn map_entities(&mut self, entity_mapper: &mut EntityMapper) {
+            self.0 = entity_mapper.get_or_reserve(self.0);
+        }
+    }
+
+    #[test]
+    fn extract_and_merge() {
+        let mut main = World::new();
+        main.init_resource::<AppTypeRegistry>();
+        main.resource::<AppTypeRegistry>()
+            .write()
+            .register::<Value>();
+
+        let spec: HierarchySpec = "x -> a; a -> b, c; b, c -> d; y -> d".parse().unwrap();
+        let entities = spec.spawn(&mut main);
+        main.entity_mut(entities["d"]).insert(Value(4));
+
+        let mut background = World::new();
+        let extracted = extract_subtree(&mut main, &mut background, entities["a"]).unwrap();
+        for name in ["a", "b", "c", "d"] {
+            assert!(main.get_entity(entities[name]).is_none());
+        }
+        assert!(main.get::<Children>(entities["x"]).is_none());
+        assert!(main.get::<Children>(entities["y"]).is_none());
+        assert_eq!(extracted.outside_parents.len(), 2);
+
+        let copy_of_d = background.get::<Children>(extracted.root).unwrap().to_vec()[0];
+        let copy_of_d = background.get::<Children>(copy_of_d).unwrap().to_vec()[0];
+        assert_eq!(background.get::<Value>(copy_of_d), Some(&Value(4)));
+        // Grow the subtree while it's away.
+        let e = background.spawn(Value(5)).id();
+        background.entity_mut(copy_of_d).push_children(&[e]);
+
+        let copies = merge_subtree(&mut background, &mut main, &extracted).unwrap();
+        assert_eq!(copies.len(), 5);
+        assert_eq!(background.entities().len(), 0);
+
+        let mut merged = [("x", entities["x"]), ("y", entities["y"])]
+            .map(|(name, entity)| (name.to_owned(), entity))
+            .into_iter()
+            .collect::<std::collections::BTreeMap<_, _>>();
+        merged.insert("a".to_owned(), copies[&extracted.root]);
+        merged.insert("d".to_owned(), copies[&copy_of_d]);
+        merged.insert("e".to_owned(), copies[&e]);
+        let [b, c] =
+            <[_; 2]>::try_from(main.get::<Children>(merged["a"]).unwrap().to_vec()).unwrap();
+        merged.insert("b".to_owned(), b);
+        merged.insert("c".to_owned(), c);
+
+        assert_hierarchy_eq!(main, merged, "x -> a; a -> b, c; b, c, y -> d; d -> e");
+        assert_eq!(main.get::<Value>(merged["e"]), Some(&Value(5)));
+    }
+
+    #[test]
+    fn transfer_entity_references() {
+        let mut main = World::new();
+        main.init_resource::<AppTypeRegistry>();
+        main.resource::<AppTypeRegistry>()
+            .write()
+            .register::<Link>();
+
+        let spec: HierarchySpec = "a -> b; x".parse().unwrap();
+        let entities = spec.spawn(&mut main);
+        main.entity_mut(entities["a"]).insert(Link(entities["b"]));
+        main.entity_mut(entities["b"]).insert(Link(entities["x"]));
+
+        let mut background = World::new();
+        // Offset the ids so that an unmapped entity would be noticed.
+        background.spawn_empty();
+        let extracted = extract_subtree(&mut main, &mut background, entities["a"]).unwrap();
+        let b = background.get::<Children>(extracted.root).unwrap().to_vec()[0];
+        assert_eq!(background.get::<Link>(extracted.root), Some(&Link(b)));
+        // `x` stayed behind, so `b` can't refer to it anymore.
+        let Link(x) = background.get::<Link>(b).unwrap();
+        assert!(background.get_entity(*x).is_none());
+
+        let copies = merge_subtree(&mut background, &mut main, &extracted).unwrap();
+        assert_eq!(
+            main.get::<Link>(copies[&extracted.root]),
+            Some(&Link(copies[&b]))
+        );
+        // Back in the same world, `b` refers to `x` again.
+        assert_eq!(main.get::<Link>(copies[&b]), Some(&Link(entities["x"])));
+    }
+}